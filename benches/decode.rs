@@ -0,0 +1,107 @@
+// Compares decoding through InstructionParser against the pre-decoded tables, on its own and
+// while emulating a long-running rom.
+//
+// `cargo bench --bench decode`, set RGBC_BENCH_ROM to run a rom instead of the built-in loop.
+
+use std::env;
+use std::hint::black_box;
+use std::path::Path;
+use std::time::{Duration, Instant};
+use rgbc::cartridge;
+use rgbc::cpu::Cpu;
+use rgbc::decode_table::DecodeTable;
+use rgbc::instructions::Opcode;
+use rgbc::memory::Memory;
+use rgbc::opcode_parser::InstructionParser;
+use rgbc::rom::Rom;
+use rgbc::rtc::{CycleClock, Rtc};
+
+const DECODE_ROUNDS: usize = 20_000;
+const ROM_CYCLES: u64 = 200_000_000;
+const CPU_HZ: f64 = 4_194_304.0;
+
+// Mixed alu, load, stack and branch loop that never leaves the first page
+const LOOP_PROGRAM: [u8; 25] = [
+    0x31, 0xFE, 0xFF, // LD SP, 0xFFFE
+    0x21, 0x00, 0xC0, // LD HL, 0xC000
+    0x7E,             // loop: LD A, (HL)
+    0x2C,             // INC L
+    0x80,             // ADD A, B
+    0xA9,             // XOR C
+    0x07,             // RLCA
+    0x13,             // INC DE
+    0x77,             // LD (HL), A
+    0xC5,             // PUSH BC
+    0xC1,             // POP BC
+    0xCD, 0x20, 0x00, // CALL 0x0020
+    0x20, 0xF2,       // JR NZ, loop
+    0xC3, 0x06, 0x00, // JP loop
+    0x00, 0x00,
+];
+
+fn bench_decode() {
+    let start = Instant::now();
+    for _ in 0..DECODE_ROUNDS {
+        for op in 0..=255u8 {
+            black_box(Opcode { value: black_box(op) }.to_instruction());
+        }
+    }
+    let parsed = start.elapsed();
+
+    let table = DecodeTable::get();
+    let start = Instant::now();
+    for _ in 0..DECODE_ROUNDS {
+        for op in 0..=255u8 {
+            black_box(table.main(black_box(op)));
+        }
+    }
+    let looked_up = start.elapsed();
+
+    let per_decode = |elapsed: Duration| elapsed.as_nanos() as f64 / (DECODE_ROUNDS * 256) as f64;
+    println!("decode: parser {:.2} ns/op, table {:.2} ns/op, {:.1}x faster",
+             per_decode(parsed), per_decode(looked_up), parsed.as_secs_f64() / looked_up.as_secs_f64());
+}
+
+// The rom in RGBC_BENCH_ROM started like a cartridge, or the built-in loop at 0x0000
+fn bench_cpu(rom: Option<&[u8]>) -> Cpu {
+    let Some(data) = rom else {
+        let mut data = vec![0; 0x100];
+        data[..LOOP_PROGRAM.len()].copy_from_slice(&LOOP_PROGRAM);
+        // Subroutine: INC B, RET
+        data[0x20] = 0x04;
+        data[0x21] = 0xC9;
+        return Cpu::new(Memory::new(Rom { data }));
+    };
+    let rtc = Rtc::new(Box::new(CycleClock::new()), 0);
+    let (header, cartridge) = cartridge::load(Rom { data: data.to_vec() }, rtc).expect("Failed to load bench rom");
+    let mut cpu = Cpu::new(Memory::with_cartridge(cartridge));
+    cpu.skip_boot_rom(header.model());
+    cpu
+}
+
+fn run_rom(rom: Option<&[u8]>, pre_decoded: bool) -> Duration {
+    let mut cpu = bench_cpu(rom);
+    cpu.set_pre_decoded(pre_decoded);
+    let start = Instant::now();
+    while cpu.cycles() < ROM_CYCLES {
+        cpu.step().expect("Bench rom failed");
+    }
+    start.elapsed()
+}
+
+fn bench_rom() {
+    let rom = env::var("RGBC_BENCH_ROM")
+        .map(|path| Rom::new(Path::new(&path)).expect("Failed to read bench rom").data)
+        .ok();
+    let parsed = run_rom(rom.as_deref(), false);
+    let looked_up = run_rom(rom.as_deref(), true);
+
+    let real_time = |elapsed: Duration| ROM_CYCLES as f64 / CPU_HZ / elapsed.as_secs_f64();
+    println!("rom: {ROM_CYCLES} cycles, parser {:.2?} ({:.1}x real time), table {:.2?} ({:.1}x real time), {:.1}x faster",
+             parsed, real_time(parsed), looked_up, real_time(looked_up), parsed.as_secs_f64() / looked_up.as_secs_f64());
+}
+
+fn main() {
+    bench_decode();
+    bench_rom();
+}
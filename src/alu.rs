@@ -0,0 +1,184 @@
+// Arithmetic, logic and shift operations on plain values, setting flags the way the sm83 does.
+// Flags not listed for an operation are left untouched.
+
+use crate::flags::Flags;
+
+// ADD and ADC, carry is the incoming carry
+pub fn add(a: u8, b: u8, carry: bool, flags: &mut Flags) -> u8 {
+    let c = carry as u8;
+    let (tmp, overflow1) = a.overflowing_add(b);
+    let (value, overflow2) = tmp.overflowing_add(c);
+
+    flags.set_z(value == 0);
+    flags.set_n(false);
+    flags.set_h((a & 0x0F) + (b & 0x0F) + c > 0x0F);
+    flags.set_c(overflow1 || overflow2);
+    value
+}
+
+// SUB, SBC and CP, carry is the incoming borrow
+pub fn sub(a: u8, b: u8, carry: bool, flags: &mut Flags) -> u8 {
+    let c = carry as u8;
+    let (tmp, overflow1) = a.overflowing_sub(b);
+    let (value, overflow2) = tmp.overflowing_sub(c);
+
+    flags.set_z(value == 0);
+    flags.set_n(true);
+    flags.set_h((a & 0x0F) < (b & 0x0F) + c);
+    flags.set_c(overflow1 || overflow2);
+    value
+}
+
+pub fn and(a: u8, b: u8, flags: &mut Flags) -> u8 {
+    let value = a & b;
+    set_logic_flags(value, true, flags);
+    value
+}
+
+pub fn xor(a: u8, b: u8, flags: &mut Flags) -> u8 {
+    let value = a ^ b;
+    set_logic_flags(value, false, flags);
+    value
+}
+
+pub fn or(a: u8, b: u8, flags: &mut Flags) -> u8 {
+    let value = a | b;
+    set_logic_flags(value, false, flags);
+    value
+}
+
+fn set_logic_flags(value: u8, h: bool, flags: &mut Flags) {
+    flags.set_z(value == 0);
+    flags.set_n(false);
+    flags.set_h(h);
+    flags.set_c(false);
+}
+
+// Carry is preserved
+pub fn inc(a: u8, flags: &mut Flags) -> u8 {
+    let value = a.wrapping_add(1);
+    flags.set_z(value == 0);
+    flags.set_n(false);
+    flags.set_h(a & 0x0F == 0x0F);
+    value
+}
+
+// Carry is preserved
+pub fn dec(a: u8, flags: &mut Flags) -> u8 {
+    let value = a.wrapping_sub(1);
+    flags.set_z(value == 0);
+    flags.set_n(true);
+    flags.set_h(a & 0x0F == 0x00);
+    value
+}
+
+// ADD HL,rr, zero is preserved and half carry comes from bit 11
+pub fn add16(a: u16, b: u16, flags: &mut Flags) -> u16 {
+    let (value, overflow) = a.overflowing_add(b);
+    flags.set_n(false);
+    flags.set_h((a & 0x0FFF) + (b & 0x0FFF) > 0x0FFF);
+    flags.set_c(overflow);
+    value
+}
+
+// ADD SP,e and LD HL,SP+e, carries come from adding the unsigned low bytes
+pub fn add_signed8(a: u16, b: i8, flags: &mut Flags) -> u16 {
+    let low = b as u8 as u16;
+    flags.set_z(false);
+    flags.set_n(false);
+    flags.set_h((a & 0x0F) + (low & 0x0F) > 0x0F);
+    flags.set_c((a & 0xFF) + low > 0xFF);
+    a.wrapping_add_signed(b as i16)
+}
+
+// RLC, or RL when rotating through the carry flag
+pub fn rotate_left(a: u8, carry: bool, flags: &mut Flags) -> u8 {
+    let bit0 = if carry { flags.c() } else { a & 0x80 != 0 };
+    set_shift_flags(a << 1 | bit0 as u8, a & 0x80 != 0, flags)
+}
+
+// RRC, or RR when rotating through the carry flag
+pub fn rotate_right(a: u8, carry: bool, flags: &mut Flags) -> u8 {
+    let bit7 = if carry { flags.c() } else { a & 0x01 != 0 };
+    set_shift_flags(a >> 1 | (bit7 as u8) << 7, a & 0x01 != 0, flags)
+}
+
+// RLCA and RLA always clear zero
+pub fn rotate_left_a(a: u8, carry: bool, flags: &mut Flags) -> u8 {
+    let value = rotate_left(a, carry, flags);
+    flags.set_z(false);
+    value
+}
+
+// RRCA and RRA always clear zero
+pub fn rotate_right_a(a: u8, carry: bool, flags: &mut Flags) -> u8 {
+    let value = rotate_right(a, carry, flags);
+    flags.set_z(false);
+    value
+}
+
+pub fn shift_left_arithmetic(a: u8, flags: &mut Flags) -> u8 {
+    set_shift_flags(a << 1, a & 0x80 != 0, flags)
+}
+
+// Bit 7 keeps its value
+pub fn shift_right_arithmetic(a: u8, flags: &mut Flags) -> u8 {
+    set_shift_flags(a >> 1 | a & 0x80, a & 0x01 != 0, flags)
+}
+
+pub fn swap_nibbles(a: u8, flags: &mut Flags) -> u8 {
+    set_shift_flags(a.rotate_left(4), false, flags)
+}
+
+pub fn shift_right_logical(a: u8, flags: &mut Flags) -> u8 {
+    set_shift_flags(a >> 1, a & 0x01 != 0, flags)
+}
+
+fn set_shift_flags(value: u8, c: bool, flags: &mut Flags) -> u8 {
+    flags.set_z(value == 0);
+    flags.set_n(false);
+    flags.set_h(false);
+    flags.set_c(c);
+    value
+}
+
+// Carry is preserved
+pub fn bit_test(a: u8, bit: u8, flags: &mut Flags) {
+    flags.set_z(a & (1 << bit) == 0);
+    flags.set_n(false);
+    flags.set_h(true);
+}
+
+// Corrects A to binary coded decimal after an addition or subtraction, negative is preserved
+pub fn decimal_adjust(a: u8, flags: &mut Flags) -> u8 {
+    let mut correction: u8 = 0;
+    let mut carry = flags.c();
+
+    if flags.h() || (!flags.n() && a & 0x0F > 0x09) {
+        correction |= 0x06;
+    }
+    if flags.c() || (!flags.n() && a > 0x99) {
+        correction |= 0x60;
+        carry = true;
+    }
+
+    let value = if flags.n() { a.wrapping_sub(correction) } else { a.wrapping_add(correction) };
+    flags.set_z(value == 0);
+    flags.set_h(false);
+    flags.set_c(carry);
+    value
+}
+
+// Zero and carry are preserved
+pub fn complement(a: u8, flags: &mut Flags) -> u8 {
+    flags.set_n(true);
+    flags.set_h(true);
+    !a
+}
+
+// SCF and CCF, zero is preserved
+pub fn set_carry(carry: bool, flags: &mut Flags) {
+    flags.set_n(false);
+    flags.set_h(false);
+    flags.set_c(carry);
+}
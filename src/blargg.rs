@@ -0,0 +1,65 @@
+use crate::cartridge;
+use crate::cpu::{Cpu, CpuMode};
+use crate::error::EmuError;
+use crate::memory::Memory;
+use crate::rom::Rom;
+use crate::rtc::{CycleClock, Rtc};
+
+// Keeps running after the verdict so the rest of the line, like the failed test number, gets printed
+const GRACE_CYCLES: u64 = 1_000_000;
+
+// Runs blargg's test roms without a frontend, they print their results over the serial port
+
+#[derive(PartialEq, Debug)]
+pub enum BlarggStatus {
+    Passed,
+    Failed,
+    // The cycle budget ran out, or the cpu stopped, before the rom reported a result
+    TimedOut,
+}
+
+#[derive(Debug)]
+pub struct BlarggResult {
+    pub status: BlarggStatus,
+    // Everything the rom printed
+    pub output: String,
+    pub cycles: u64,
+}
+
+pub fn run(rom: Rom, cycle_budget: u64) -> Result<BlarggResult, EmuError> {
+    let (header, cartridge) = cartridge::load(rom, Rtc::new(Box::new(CycleClock::new()), 0))?;
+    let mut cpu = Cpu::new(Memory::with_cartridge(cartridge));
+    // The cgb only roms check for a cgb through the boot rom's A register
+    cpu.skip_boot_rom(header.model());
+    cpu.mem.serial.set_capture(true);
+
+    let mut printed = 0;
+    let mut status = BlarggStatus::TimedOut;
+    let mut end = cycle_budget;
+    while cpu.cycles() < end {
+        cpu.step()?;
+        // Nothing presses a button without a frontend, a stopped cpu never wakes up
+        if *cpu.mode() == CpuMode::Stopped { break; }
+
+        // Only look for the verdict when something new was printed
+        let output = cpu.mem.serial.output();
+        if status != BlarggStatus::TimedOut || output.len() == printed { continue; }
+        printed = output.len();
+
+        let text = String::from_utf8_lossy(output);
+        if text.contains("Passed") {
+            status = BlarggStatus::Passed;
+        } else if text.contains("Failed") {
+            status = BlarggStatus::Failed;
+        } else {
+            continue;
+        }
+        end = cpu.cycles() + GRACE_CYCLES;
+    }
+
+    Ok(BlarggResult {
+        status,
+        output: String::from_utf8_lossy(cpu.mem.serial.output()).into_owned(),
+        cycles: cpu.cycles(),
+    })
+}
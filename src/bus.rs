@@ -0,0 +1,99 @@
+use crate::interrupts::Interrupt;
+use crate::model::Model;
+
+// Everything the cpu reaches through its address bus. Only the accesses are required, the
+// system hooks default to a bus with nothing but memory behind it.
+pub trait Bus {
+    fn read8(&mut self, addr: u16) -> u8;
+    fn write8(&mut self, addr: u16, value: u8);
+    // Read without side effects, for debuggers and tracing
+    fn peek(&self, addr: u16) -> u8;
+
+    // Advances the rest of the system, called with 4 cpu cycles for every m-cycle
+    fn tick(&mut self, _cycles: u8) {}
+
+    // Leaves everything but the cpu registers the way the boot rom of the model does
+    fn skip_boot_rom(&mut self, _model: Model) {}
+
+    // Highest priority interrupt that is both requested and enabled
+    fn pending_interrupt(&self) -> Option<Interrupt> { None }
+    fn acknowledge_interrupt(&mut self, _interrupt: Interrupt) {}
+
+    // Resets DIV and performs an armed speed switch on cgb, returns true if the speed switched
+    fn stop(&mut self) -> bool { false }
+    // Wakes the cpu from STOP
+    fn any_button_pressed(&self) -> bool { false }
+
+    // Rom bank mapped at addr, for error reports
+    fn rom_bank(&self, _addr: u16) -> u16 { 0 }
+}
+
+// Plain 64 KiB of ram without io registers or interrupts, for cpu conformance tests
+pub struct FlatBus {
+    pub data: Box<[u8; 0x10000]>,
+}
+
+impl FlatBus {
+    pub fn new() -> Self {
+        FlatBus { data: Box::new([0; 0x10000]) }
+    }
+}
+
+impl Default for FlatBus {
+    fn default() -> Self {
+        FlatBus::new()
+    }
+}
+
+impl Bus for FlatBus {
+    fn read8(&mut self, addr: u16) -> u8 { self.data[addr as usize] }
+    fn write8(&mut self, addr: u16, value: u8) { self.data[addr as usize] = value }
+    fn peek(&self, addr: u16) -> u8 { self.data[addr as usize] }
+}
+
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum Access {
+    Read { addr: u16, value: u8 },
+    Write { addr: u16, value: u8 },
+    // One m-cycle, the cpu ticks before the access made in the same m-cycle
+    Tick,
+}
+
+// Wraps another bus and records every access and tick in order
+pub struct RecordingBus<B: Bus> {
+    pub inner: B,
+    pub accesses: Vec<Access>,
+}
+
+impl<B: Bus> RecordingBus<B> {
+    pub fn new(inner: B) -> Self {
+        RecordingBus { inner, accesses: Vec::new() }
+    }
+}
+
+impl<B: Bus> Bus for RecordingBus<B> {
+    fn read8(&mut self, addr: u16) -> u8 {
+        let value = self.inner.read8(addr);
+        self.accesses.push(Access::Read { addr, value });
+        value
+    }
+
+    fn write8(&mut self, addr: u16, value: u8) {
+        self.inner.write8(addr, value);
+        self.accesses.push(Access::Write { addr, value });
+    }
+
+    fn peek(&self, addr: u16) -> u8 { self.inner.peek(addr) }
+
+    fn tick(&mut self, cycles: u8) {
+        self.inner.tick(cycles);
+        self.accesses.push(Access::Tick);
+    }
+
+    fn skip_boot_rom(&mut self, model: Model) { self.inner.skip_boot_rom(model) }
+    fn pending_interrupt(&self) -> Option<Interrupt> { self.inner.pending_interrupt() }
+    fn acknowledge_interrupt(&mut self, interrupt: Interrupt) { self.inner.acknowledge_interrupt(interrupt) }
+    fn stop(&mut self) -> bool { self.inner.stop() }
+    fn any_button_pressed(&self) -> bool { self.inner.any_button_pressed() }
+    fn rom_bank(&self, addr: u16) -> u16 { self.inner.rom_bank(addr) }
+}
@@ -0,0 +1,395 @@
+use crate::error::EmuError;
+use crate::header::{CartridgeHeader, NINTENDO_LOGO};
+use crate::rom::Rom;
+use crate::rtc::{Rtc, SAVE_STATE_SIZE};
+
+// Memory bank controller inside the cartridge, owns the rom and the external ram.
+// Rom addresses are 0x0000-0x7FFF and ram addresses 0xA000-0xBFFF.
+pub trait Mapper {
+    fn read_rom(&self, addr: u16) -> u8;
+    // Writes to rom addresses program the mapper registers
+    fn write_rom(&mut self, addr: u16, value: u8);
+    fn read_ram(&self, addr: u16) -> u8;
+    fn write_ram(&mut self, addr: u16, value: u8);
+    // Rom bank mapped at addr
+    fn rom_bank(&self, addr: u16) -> u16;
+    // Lcd clock cycles, 4 MHz in both speeds
+    fn tick(&mut self, _cycles: u8) {}
+    // New state of the rumble motor if it changed since the last call
+    fn take_rumble(&mut self) -> Option<bool> { None }
+
+    // Contents of the ram as kept in save files
+    fn save_data(&self) -> Vec<u8> { Vec::new() }
+    // Saves of a different size are loaded as far as they fit
+    fn load_save_data(&mut self, _data: &[u8]) {}
+}
+
+fn load_ram(ram: &mut [u8], data: &[u8]) {
+    let len = ram.len().min(data.len());
+    ram[..len].copy_from_slice(&data[..len]);
+}
+
+// Cartridge without a mapper, 32 KiB of rom and up to 8 KiB of ram
+pub struct RomOnly {
+    rom: Vec<u8>,
+    ram: Vec<u8>,
+}
+
+impl RomOnly {
+    pub fn new(rom: Vec<u8>, ram_size: usize) -> Self {
+        RomOnly { rom, ram: vec![0; ram_size] }
+    }
+}
+
+impl Mapper for RomOnly {
+    // Addresses past the end of the rom are open bus
+    fn read_rom(&self, addr: u16) -> u8 {
+        self.rom.get(addr as usize).copied().unwrap_or(0xFF)
+    }
+
+    fn write_rom(&mut self, _addr: u16, _value: u8) {}
+
+    fn read_ram(&self, addr: u16) -> u8 {
+        self.ram.get((addr - 0xA000) as usize).copied().unwrap_or(0xFF)
+    }
+
+    fn write_ram(&mut self, addr: u16, value: u8) {
+        if let Some(byte) = self.ram.get_mut((addr - 0xA000) as usize) {
+            *byte = value;
+        }
+    }
+
+    fn rom_bank(&self, addr: u16) -> u16 {
+        if addr < 0x4000 { 0 } else { 1 }
+    }
+
+    fn save_data(&self) -> Vec<u8> { self.ram.clone() }
+    fn load_save_data(&mut self, data: &[u8]) { load_ram(&mut self.ram, data) }
+}
+
+// Bank number masked to the banks the rom has, as an offset into the rom
+fn rom_offset(rom: &[u8], bank: usize, addr: u16) -> usize {
+    let banks = (rom.len() / 0x4000).max(1);
+    (bank % banks) * 0x4000 + (addr & 0x3FFF) as usize
+}
+
+// Up to 2 MiB of rom and 32 KiB of ram. Multicarts (MBC1M) wire only four bits of the low bank
+// register, so every game is 256 KiB.
+pub struct Mbc1 {
+    rom: Vec<u8>,
+    ram: Vec<u8>,
+    ram_enabled: bool,
+    // Five bit low rom bank, zero selects bank 1
+    bank1: u8,
+    // Two bits, upper rom bank bits or ram bank
+    bank2: u8,
+    // Set to apply bank2 to 0x0000-0x3FFF and to ram
+    advanced_mode: bool,
+    multicart: bool,
+}
+
+impl Mbc1 {
+    pub fn new(rom: Vec<u8>, ram_size: usize) -> Self {
+        let multicart = Mbc1::is_multicart(&rom);
+        Mbc1 { rom, ram: vec![0; ram_size], ram_enabled: false, bank1: 1, bank2: 0, advanced_mode: false, multicart }
+    }
+
+    // 1 MiB multicarts repeat the logo in the header of the game in bank 0x10
+    pub fn is_multicart(rom: &[u8]) -> bool {
+        rom.len() == 0x100000 && rom[0x40104..0x40134] == NINTENDO_LOGO
+    }
+
+    fn upper_bits(&self) -> usize {
+        let shift = if self.multicart { 4 } else { 5 };
+        (self.bank2 as usize) << shift
+    }
+
+    fn bank(&self, addr: u16) -> usize {
+        if addr < 0x4000 {
+            if self.advanced_mode { self.upper_bits() } else { 0 }
+        } else {
+            let mask = if self.multicart { 0x0F } else { 0x1F };
+            self.upper_bits() | (self.bank1 & mask) as usize
+        }
+    }
+
+    fn ram_offset(&self, addr: u16) -> Option<usize> {
+        if !self.ram_enabled || self.ram.is_empty() { return None; }
+        let bank = if self.advanced_mode { self.bank2 as usize } else { 0 };
+        Some((bank * 0x2000 + (addr - 0xA000) as usize) % self.ram.len())
+    }
+}
+
+impl Mapper for Mbc1 {
+    fn read_rom(&self, addr: u16) -> u8 {
+        self.rom.get(rom_offset(&self.rom, self.bank(addr), addr)).copied().unwrap_or(0xFF)
+    }
+
+    fn write_rom(&mut self, addr: u16, value: u8) {
+        match addr {
+            0x0000..=0x1FFF => self.ram_enabled = value & 0x0F == 0x0A,
+            0x2000..=0x3FFF => self.bank1 = (value & 0x1F).max(1),
+            0x4000..=0x5FFF => self.bank2 = value & 0x03,
+            _ => self.advanced_mode = value & 0x01 != 0,
+        }
+    }
+
+    // Disabled ram is open bus
+    fn read_ram(&self, addr: u16) -> u8 {
+        self.ram_offset(addr).map_or(0xFF, |offset| self.ram[offset])
+    }
+
+    fn write_ram(&mut self, addr: u16, value: u8) {
+        if let Some(offset) = self.ram_offset(addr) {
+            self.ram[offset] = value;
+        }
+    }
+
+    fn rom_bank(&self, addr: u16) -> u16 {
+        (rom_offset(&self.rom, self.bank(addr), addr) / 0x4000) as u16
+    }
+
+    fn save_data(&self) -> Vec<u8> { self.ram.clone() }
+    fn load_save_data(&mut self, data: &[u8]) { load_ram(&mut self.ram, data) }
+}
+
+// Up to 2 MiB of rom, 32 KiB of ram and an optional real time clock
+pub struct Mbc3 {
+    rom: Vec<u8>,
+    ram: Vec<u8>,
+    // Enables the clock registers as well
+    ram_enabled: bool,
+    // Seven bits, zero selects bank 1
+    bank: u8,
+    // Ram bank 0x00-0x03 or clock register 0x08-0x0C
+    ram_select: u8,
+    // Last value written to 0x6000-0x7FFF, writing 0 then 1 latches the clock
+    latch: u8,
+    rtc: Option<Rtc>,
+}
+
+impl Mbc3 {
+    pub fn new(rom: Vec<u8>, ram_size: usize, rtc: Option<Rtc>) -> Self {
+        Mbc3 { rom, ram: vec![0; ram_size], ram_enabled: false, bank: 1, ram_select: 0, latch: 0xFF, rtc }
+    }
+
+    fn ram_offset(&self, addr: u16) -> Option<usize> {
+        if !self.ram_enabled || self.ram_select > 0x03 || self.ram.is_empty() { return None; }
+        Some((self.ram_select as usize * 0x2000 + (addr - 0xA000) as usize) % self.ram.len())
+    }
+
+    // Clock register selected in place of a ram bank
+    fn rtc_register(&self) -> Option<u8> {
+        (self.ram_enabled && (0x08..=0x0C).contains(&self.ram_select)).then_some(self.ram_select)
+    }
+}
+
+impl Mapper for Mbc3 {
+    fn read_rom(&self, addr: u16) -> u8 {
+        self.rom.get(rom_offset(&self.rom, self.rom_bank(addr) as usize, addr)).copied().unwrap_or(0xFF)
+    }
+
+    fn write_rom(&mut self, addr: u16, value: u8) {
+        match addr {
+            0x0000..=0x1FFF => self.ram_enabled = value & 0x0F == 0x0A,
+            0x2000..=0x3FFF => self.bank = (value & 0x7F).max(1),
+            0x4000..=0x5FFF => self.ram_select = value,
+            _ => {
+                if self.latch == 0x00 && value == 0x01 {
+                    if let Some(rtc) = &mut self.rtc { rtc.latch() }
+                }
+                self.latch = value;
+            }
+        }
+    }
+
+    fn read_ram(&self, addr: u16) -> u8 {
+        match (self.rtc_register(), &self.rtc) {
+            (Some(register), Some(rtc)) => rtc.read(register),
+            _ => self.ram_offset(addr).map_or(0xFF, |offset| self.ram[offset]),
+        }
+    }
+
+    fn write_ram(&mut self, addr: u16, value: u8) {
+        match (self.rtc_register(), &mut self.rtc) {
+            (Some(register), Some(rtc)) => rtc.write(register, value),
+            _ => if let Some(offset) = self.ram_offset(addr) { self.ram[offset] = value },
+        }
+    }
+
+    fn rom_bank(&self, addr: u16) -> u16 {
+        let bank = if addr < 0x4000 { 0 } else { self.bank as usize };
+        (rom_offset(&self.rom, bank, addr) / 0x4000) as u16
+    }
+
+    fn tick(&mut self, cycles: u8) {
+        if let Some(rtc) = &mut self.rtc { rtc.tick(cycles) }
+    }
+
+    // The clock follows the ram
+    fn save_data(&self) -> Vec<u8> {
+        let mut data = self.ram.clone();
+        if let Some(rtc) = &self.rtc { data.extend(rtc.save_state()) }
+        data
+    }
+
+    fn load_save_data(&mut self, data: &[u8]) {
+        load_ram(&mut self.ram, data);
+        let footer = data.get(self.ram.len()..self.ram.len() + SAVE_STATE_SIZE);
+        if let (Some(rtc), Some(footer)) = (&mut self.rtc, footer) {
+            rtc.load_state(footer.try_into().unwrap());
+        }
+    }
+}
+
+// Up to 8 MiB of rom and 128 KiB of ram, any bank including 0 can be mapped at 0x4000
+pub struct Mbc5 {
+    rom: Vec<u8>,
+    ram: Vec<u8>,
+    ram_enabled: bool,
+    // Nine bits
+    bank: u16,
+    ram_bank: u8,
+    // Rumble cartridges drive the motor with bit 3 of the ram bank register
+    has_rumble: bool,
+    motor: bool,
+    motor_changed: bool,
+}
+
+impl Mbc5 {
+    pub fn new(rom: Vec<u8>, ram_size: usize, has_rumble: bool) -> Self {
+        Mbc5 { rom, ram: vec![0; ram_size], ram_enabled: false, bank: 1, ram_bank: 0, has_rumble, motor: false, motor_changed: false }
+    }
+
+    fn ram_offset(&self, addr: u16) -> Option<usize> {
+        if !self.ram_enabled || self.ram.is_empty() { return None; }
+        Some((self.ram_bank as usize * 0x2000 + (addr - 0xA000) as usize) % self.ram.len())
+    }
+}
+
+impl Mapper for Mbc5 {
+    fn read_rom(&self, addr: u16) -> u8 {
+        self.rom.get(rom_offset(&self.rom, self.rom_bank(addr) as usize, addr)).copied().unwrap_or(0xFF)
+    }
+
+    fn write_rom(&mut self, addr: u16, value: u8) {
+        match addr {
+            // All eight bits are compared
+            0x0000..=0x1FFF => self.ram_enabled = value == 0x0A,
+            0x2000..=0x2FFF => self.bank = self.bank & 0x100 | value as u16,
+            0x3000..=0x3FFF => self.bank = self.bank & 0xFF | (value as u16 & 0x01) << 8,
+            0x4000..=0x5FFF if self.has_rumble => {
+                self.ram_bank = value & 0x07;
+                let motor = value & 0x08 != 0;
+                self.motor_changed |= motor != self.motor;
+                self.motor = motor;
+            }
+            0x4000..=0x5FFF => self.ram_bank = value & 0x0F,
+            _ => {}
+        }
+    }
+
+    fn read_ram(&self, addr: u16) -> u8 {
+        self.ram_offset(addr).map_or(0xFF, |offset| self.ram[offset])
+    }
+
+    fn write_ram(&mut self, addr: u16, value: u8) {
+        if let Some(offset) = self.ram_offset(addr) {
+            self.ram[offset] = value;
+        }
+    }
+
+    fn rom_bank(&self, addr: u16) -> u16 {
+        let bank = if addr < 0x4000 { 0 } else { self.bank as usize };
+        (rom_offset(&self.rom, bank, addr) / 0x4000) as u16
+    }
+
+    fn take_rumble(&mut self) -> Option<bool> {
+        std::mem::take(&mut self.motor_changed).then_some(self.motor)
+    }
+
+    fn save_data(&self) -> Vec<u8> { self.ram.clone() }
+    fn load_save_data(&mut self, data: &[u8]) { load_ram(&mut self.ram, data) }
+}
+
+// Up to 256 KiB of rom and 512 half bytes of ram inside the mapper itself
+pub struct Mbc2 {
+    rom: Vec<u8>,
+    // Only the low nibble of every byte is stored
+    ram: [u8; 0x200],
+    ram_enabled: bool,
+    // Four bits, zero selects bank 1
+    bank: u8,
+}
+
+impl Mbc2 {
+    pub fn new(rom: Vec<u8>) -> Self {
+        Mbc2 { rom, ram: [0; 0x200], ram_enabled: false, bank: 1 }
+    }
+}
+
+impl Mapper for Mbc2 {
+    fn read_rom(&self, addr: u16) -> u8 {
+        self.rom.get(rom_offset(&self.rom, self.rom_bank(addr) as usize, addr)).copied().unwrap_or(0xFF)
+    }
+
+    // Address bit 8 picks the register in 0x0000-0x3FFF, the upper half has none
+    fn write_rom(&mut self, addr: u16, value: u8) {
+        match addr {
+            0x0000..=0x3FFF if addr & 0x0100 != 0 => self.bank = (value & 0x0F).max(1),
+            0x0000..=0x3FFF => self.ram_enabled = value & 0x0F == 0x0A,
+            _ => {}
+        }
+    }
+
+    // Mirrored every 512 bytes, the upper nibble reads as set
+    fn read_ram(&self, addr: u16) -> u8 {
+        if !self.ram_enabled { return 0xFF; }
+        0xF0 | self.ram[(addr & 0x01FF) as usize]
+    }
+
+    fn write_ram(&mut self, addr: u16, value: u8) {
+        if self.ram_enabled {
+            self.ram[(addr & 0x01FF) as usize] = value & 0x0F;
+        }
+    }
+
+    fn rom_bank(&self, addr: u16) -> u16 {
+        let bank = if addr < 0x4000 { 0 } else { self.bank as usize };
+        (rom_offset(&self.rom, bank, addr) / 0x4000) as u16
+    }
+
+    // One byte per half byte, as other emulators store it
+    fn save_data(&self) -> Vec<u8> { self.ram.to_vec() }
+
+    fn load_save_data(&mut self, data: &[u8]) {
+        load_ram(&mut self.ram, data);
+        self.ram.iter_mut().for_each(|byte| *byte &= 0x0F);
+    }
+}
+
+// Parses the header and builds the mapper its cartridge type names, cartridges with a real
+// time clock get the rtc
+pub fn load(rom: Rom, rtc: Rtc) -> Result<(CartridgeHeader, Box<dyn Mapper>), EmuError> {
+    let header = CartridgeHeader::parse(&rom.data)?;
+    let invalid = |reason: String| EmuError::InvalidRomHeader { reason };
+    if header.rom_size().is_none() {
+        return Err(invalid(format!("unknown rom size code {:#04x}", header.rom_size_code)));
+    }
+    let ram_size = header.ram_size()
+        .ok_or_else(|| invalid(format!("unknown ram size code {:#04x}", header.ram_size_code)))?;
+
+    let mapper: Box<dyn Mapper> = match header.cartridge_type {
+        // Optionally with ram and a battery
+        0x00 | 0x08 | 0x09 => Box::new(RomOnly::new(rom.data, ram_size)),
+        0x01..=0x03 => Box::new(Mbc1::new(rom.data, ram_size)),
+        // The ram size in the header is zero, the ram is part of the mapper
+        0x05 | 0x06 => Box::new(Mbc2::new(rom.data)),
+        0x0F | 0x10 => Box::new(Mbc3::new(rom.data, ram_size, Some(rtc))),
+        0x11..=0x13 => Box::new(Mbc3::new(rom.data, ram_size, None)),
+        0x19..=0x1B => Box::new(Mbc5::new(rom.data, ram_size, false)),
+        0x1C..=0x1E => Box::new(Mbc5::new(rom.data, ram_size, true)),
+        code => return Err(invalid(format!("cartridge type {code:#04x} is not supported"))),
+    };
+    Ok((header, mapper))
+}
@@ -1,54 +1,212 @@
 use std::fmt::{Debug, Formatter};
+use crate::bus::Bus;
+use crate::decode_table::DecodeTable;
+use crate::opcode_parser::InstructionParser;
+use crate::error::{EmuError, ErrorContext};
+use crate::alu;
 use crate::flags::{Flag, Flags};
 use crate::registers::*;
+use crate::trace::TraceWriter;
 use crate::instructions::*;
 use crate::memory::*;
-use crate::opcode_parser::*;
+use crate::model::Model;
 
 
+const LD_B_B: u8 = 0x40;
+
 #[derive(Debug)]
 pub struct ProgramCounter {
     pub value: u16,
 }
 
-pub struct Cpu {
+#[derive(PartialEq, Debug)]
+pub enum CpuMode {
+    Running,
+    // Waiting for an enabled interrupt, the rest of the system keeps running
+    Halted,
+    // Waiting for joypad input, the system clock is stopped
+    Stopped,
+    // Hung by an illegal opcode, only a reset recovers
+    Locked,
+}
+
+// Runs over any bus, by default the full memory map
+pub struct Cpu<B: Bus = Memory> {
     regs: Registers,
-    flags: Flags,
-    pub mem: Memory,
+    pub mem: B,
     pc: ProgramCounter,
-    cycles: u16,
+    // Total cpu cycles since power on
+    cycles: u64,
+    // Interrupt master enable
+    ime: bool,
+    // EI takes effect after the instruction following it
+    ime_scheduled: bool,
+    mode: CpuMode,
+    // HALT with IME=0 and a pending interrupt fails to increment pc after the next fetch
+    halt_bug: bool,
+    // Address of the instruction being executed
+    instruction_pc: u16,
+    // Raise errors for illegal opcodes and suspicious accesses instead of carrying on like hardware
+    strict: bool,
+    // Parses every opcode again when None, to compare against the tables
+    decode_table: Option<&'static DecodeTable>,
+    // Logs the state before every instruction when set
+    trace: Option<TraceWriter>,
+    // Set when LD B,B runs, test roms use it as a software breakpoint
+    breakpoint: bool,
 }
 
-impl Debug for Cpu {
+impl<B: Bus + Debug> Debug for Cpu<B> {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(f, "Cpu state:\n{:?} \n{:?} \n{:?} \nCycles: {:?} \nMemory:\n{:?}", self.regs, self.flags, self.pc, self.cycles, self.mem)
+        write!(f, "Cpu state:\n{:?} \n{:?} \n{:?} \nIme: {:?} \nMode: {:?} \nCycles: {:?} \nMemory:\n{:?}", self.regs, self.regs.f, self.pc, self.ime, self.mode, self.cycles, self.mem)
     }
 }
 
-impl Cpu {
-    pub fn new(mem: Memory) -> Self {
+impl<B: Bus> Cpu<B> {
+    pub fn new(mem: B) -> Self {
         Cpu {
             regs: Registers::new(),
-            flags: Flags::new(),
             mem,
             pc: ProgramCounter { value: 0 },
             cycles: 0,
+            ime: false,
+            ime_scheduled: false,
+            mode: CpuMode::Running,
+            halt_bug: false,
+            instruction_pc: 0,
+            strict: false,
+            decode_table: Some(DecodeTable::get()),
+            trace: None,
+            breakpoint: false,
         }
     }
 
-    pub fn step(&mut self) {
-        let opcode = Opcode { value: self.read_pcaddr8() };
-        let instruction = opcode.to_instruction();
-        // println!("Executing instruction {opcode:?}, {instruction:?}");
-        self.execute_instruction(&instruction);
-        self.update_cycles(&instruction, &opcode);
+    pub fn set_strict(&mut self, strict: bool) {
+        self.strict = strict;
+    }
+
+    pub fn set_pre_decoded(&mut self, pre_decoded: bool) {
+        self.decode_table = pre_decoded.then(DecodeTable::get);
+    }
+
+    pub fn set_trace(&mut self, trace: Option<TraceWriter>) {
+        self.trace = trace;
+    }
+
+    // Whether LD B,B ran since the last call
+    pub fn take_breakpoint(&mut self) -> bool {
+        std::mem::take(&mut self.breakpoint)
+    }
+
+    pub fn mode(&self) -> &CpuMode {
+        &self.mode
+    }
+
+    pub fn cycles(&self) -> u64 {
+        self.cycles
+    }
+
+    pub fn registers(&self) -> &Registers {
+        &self.regs
+    }
+
+    pub fn registers_mut(&mut self) -> &mut Registers {
+        &mut self.regs
     }
 
-    fn execute_instruction(&mut self, instruction: &Instruction) {
+    pub fn pc(&self) -> u16 {
+        self.pc.value
+    }
+
+    pub fn set_pc(&mut self, value: u16) {
+        self.pc.value = value;
+    }
+
+    // State the boot rom of the model leaves behind, for starting a cartridge directly at 0x0100
+    pub fn skip_boot_rom(&mut self, model: Model) {
+        let header_checksum = self.mem.peek(0x014D);
+        let cgb_cartridge = self.mem.peek(0x0143) & 0x80 != 0;
+        let [af, bc, de, hl] = match model {
+            // Carry and half carry stay clear only when the header checksum is zero
+            Model::Dmg => [if header_checksum == 0 { 0x0180 } else { 0x01B0 }, 0x0013, 0x00D8, 0x014D],
+            Model::Mgb => [if header_checksum == 0 { 0xFF80 } else { 0xFFB0 }, 0x0013, 0x00D8, 0x014D],
+            Model::Cgb if cgb_cartridge => [0x1180, 0x0000, 0xFF56, 0x000D],
+            Model::Cgb => [0x1180, 0x0000, 0x0008, 0x007C],
+            Model::Agb if cgb_cartridge => [0x1100, 0x0100, 0xFF56, 0x000D],
+            Model::Agb => [0x1100, 0x0100, 0x0008, 0x007C],
+        };
+        self.regs.af_w(af);
+        self.regs.bc_w(bc);
+        self.regs.de_w(de);
+        self.regs.hl_w(hl);
+        self.regs.sp = 0xFFFE;
+        self.pc.value = 0x0100;
+        self.mem.skip_boot_rom(model);
+    }
+
+    // Runs one instruction, interrupt dispatch or idle m-cycle, and returns the t-cycles it took
+    pub fn step(&mut self) -> Result<u8, EmuError> {
+        let start = self.cycles;
+        self.step_mode()?;
+        Ok((self.cycles - start) as u8)
+    }
+
+    fn step_mode(&mut self) -> Result<(), EmuError> {
+        match self.mode {
+            CpuMode::Running => {}
+            CpuMode::Halted => {
+                if self.mem.pending_interrupt().is_none() {
+                    self.tick();
+                    return Ok(());
+                }
+                self.mode = CpuMode::Running;
+            }
+            CpuMode::Stopped => {
+                if !self.mem.any_button_pressed() { return Ok(()); }
+                self.mode = CpuMode::Running;
+            }
+            CpuMode::Locked => {
+                // No fetches and no interrupts, but the rest of the system keeps running
+                self.tick();
+                return Ok(());
+            }
+        }
+
+        if self.service_interrupt() { return Ok(()); }
+
+        if self.ime_scheduled {
+            self.ime = true;
+            self.ime_scheduled = false;
+        }
+
+        if let Some(trace) = &mut self.trace {
+            if let Err(e) = trace.write(&self.regs, self.pc.value, &self.mem) {
+                eprintln!("Trace disabled: {e}");
+                self.trace = None;
+            }
+        }
+
+        self.instruction_pc = self.pc.value;
+        let opcode = Opcode { value: self.fetch_opcode()? };
+        if opcode.value == LD_B_B { self.breakpoint = true; }
+        let parsed;
+        let instruction = match self.decode_table {
+            Some(table) => table.main(opcode.value).map(|descriptor| &descriptor.instruction),
+            None => {
+                parsed = opcode.to_instruction();
+                parsed.as_ref()
+            }
+        };
+        let instruction = instruction
+            .ok_or_else(|| EmuError::UnimplementedOpcode(self.error_context(opcode.value)))?;
+        self.execute_instruction(instruction)
+    }
+
+    fn execute_instruction(&mut self, instruction: &Instruction) -> Result<(), EmuError> {
         match instruction {
             Instruction::Noop => {}
-            Instruction::Stop => {} // Todo switch speed
-            Instruction::Halt => {} // Todo halt,
+            Instruction::Stop => self.stop(),
+            Instruction::Halt => self.halt(),
 
             Instruction::AddA { src, carry } => self.add_a(src, carry),
             Instruction::AddHl { src } => self.add_hl(src),
@@ -85,203 +243,204 @@ impl Cpu {
             Instruction::ReturnInterrupt => self.ret_interrupt(),
             Instruction::Restart { addr } => self.restart(addr),
 
-            Instruction::DecimalAdjustA => todo!(),
-            Instruction::ComplementA => todo!(),
+            Instruction::DecimalAdjustA => self.decimal_adjust_a(),
+            Instruction::ComplementA => self.complement_a(),
             Instruction::SetCarryFlag => self.set_carry_flag(),
             Instruction::ComplementCarryFlag => self.complement_carry_flag(),
-            Instruction::SetInterrupts { enable } => todo!(),
+            Instruction::SetInterrupts { enable } => self.set_interrupts(enable),
 
             Instruction::NestedInstruction => {
                 let opcode = Opcode { value: self.read_pcaddr8() };
-                let bit_instruction = opcode.to_bit_instruction();
-                println!("Executing bit instruction {opcode:?}, {bit_instruction:?}");
-                self.execute_bit_instruction(&bit_instruction);
-                // TODO cycles
+                match self.decode_table {
+                    Some(table) => self.execute_bit_instruction(&table.cb(opcode.value).instruction),
+                    None => self.execute_bit_instruction(&opcode.to_bit_instruction()),
+                }
             }
-            _ => panic!("Instruction {instruction:?} not implemented")
+            Instruction::Illegal { opcode } => self.lock_up(opcode)?,
+        }
+        Ok(())
+    }
+
+    fn fetch_opcode(&mut self) -> Result<u8, EmuError> {
+        // Unusable memory and io registers never hold code, so executing them means the program crashed
+        if self.strict && (0xFEA0..=0xFF7F).contains(&self.pc.value) {
+            let context = self.error_context(self.mem.peek(self.pc.value));
+            return Err(EmuError::BusFault { addr: self.pc.value, context });
+        }
+
+        let value = self.read_pcaddr8();
+        if self.halt_bug {
+            self.halt_bug = false;
+            self.pc.value = self.pc.value.wrapping_sub(1);
         }
+        Ok(value)
     }
 
-    fn update_cycles(&mut self, instruction: &Instruction, opcode: &Opcode) {
-        self.cycles += instruction.cycles(opcode, &self.flags) as u16;
+    fn error_context(&self, opcode: u8) -> ErrorContext {
+        ErrorContext {
+            pc: self.instruction_pc,
+            opcode,
+            // Code outside the rom has no bank
+            bank: if self.instruction_pc < 0x8000 { self.mem.rom_bank(self.instruction_pc) } else { 0 },
+            cycles: self.cycles,
+        }
+    }
+
+    // Advances the rest of the system by one m-cycle, every bus access and internal delay takes one
+    fn tick(&mut self) {
+        self.update_cycles(4);
+    }
 
-        if self.cycles >= 456 {
-            self.cycles = 0;
+    fn update_cycles(&mut self, cycles: u8) {
+        self.cycles += cycles as u64;
+        self.mem.tick(cycles);
+    }
+
+    // Dispatches the highest priority pending interrupt, if interrupts are enabled
+    fn service_interrupt(&mut self) -> bool {
+        if !self.ime || self.mem.pending_interrupt().is_none() { return false; }
 
-            let scanline: u8 = self.mem.read_addr8(0xFF44);
-            let value = if scanline > 153 { 0 } else { scanline + 1 };
+        self.ime = false;
+        self.tick();
+        self.tick();
 
-            self.mem.write_addr8(0xFF44, value);
+        let [low, high] = self.pc.value.to_le_bytes();
+        self.regs.sp = self.regs.sp.wrapping_sub(1);
+        self.write_addr8(self.regs.sp, high);
+        // The interrupt is picked after pushing the high byte, which may have overwritten IE
+        let interrupt = self.mem.pending_interrupt();
+        self.regs.sp = self.regs.sp.wrapping_sub(1);
+        self.write_addr8(self.regs.sp, low);
 
-            if value == 144 {
-                // Todo interrupt
+        self.pc.value = match interrupt {
+            Some(interrupt) => {
+                self.mem.acknowledge_interrupt(interrupt);
+                interrupt.vector()
             }
+            None => 0x0000
+        };
+        self.tick();
+        true
+    }
+
+    fn halt(&mut self) {
+        if !self.ime && self.mem.pending_interrupt().is_some() {
+            // Halt is skipped, and the byte after it is read twice
+            self.halt_bug = true;
+        } else {
+            self.mode = CpuMode::Halted;
         }
     }
 
-    fn add_a(&mut self, src: &OpsTarget8, carry: &bool) {
-        let a: u8 = self.regs.a;
-        let b: u8 = self.read_opst8(src);
-        let c: bool = *carry && self.flags.c;
+    fn stop(&mut self) {
+        // Stop is followed by an ignored byte
+        self.pc.value = self.pc.value.wrapping_add(1);
 
-        let (value, overflow) = a.carrying_add(b, c);
-        self.regs.a = value;
+        // After a cgb speed switch execution resumes at the new speed
+        if !self.mem.stop() {
+            self.mode = CpuMode::Stopped;
+        }
+    }
 
-        self.flags.z = value == 0;
-        self.flags.n = false;
-        self.flags.h = (a << 4).carrying_add(b << 4, c).1;
-        self.flags.c = overflow;
+    fn lock_up(&mut self, opcode: &u8) -> Result<(), EmuError> {
+        self.mode = CpuMode::Locked;
+        if self.strict {
+            return Err(EmuError::IllegalOpcode(self.error_context(*opcode)));
+        }
+        Ok(())
     }
 
-    fn add_hl(&mut self, src: &Register16) {
-        let a: u16 = self.regs.hl();
-        let b: u16 = self.regs.read_reg16(src);
+    fn add_a(&mut self, src: &OpsTarget8, carry: &bool) {
+        let a = self.regs.a;
+        let b = self.read_opst8(src);
+        let c = *carry && self.regs.f.c();
+        self.regs.a = alu::add(a, b, c, &mut self.regs.f);
+    }
 
-        let (value, overflow) = a.overflowing_add(b);
+    fn add_hl(&mut self, src: &Register16) {
+        let a = self.regs.hl();
+        let b = self.regs.read_reg16(src);
+        let value = alu::add16(a, b, &mut self.regs.f);
         self.regs.hl_w(value);
-
-        self.flags.z = value == 0;
-        self.flags.n = false;
-        self.flags.h = (a << 4).overflowing_add(b << 4).1;
-        self.flags.c = overflow;
+        self.tick();
     }
 
     fn add_8_to_sp(&mut self) {
-        // TODO i8
-        let a: u16 = self.pc.value;
-        let b: u16 = self.read_pcaddr8() as u16;
-
-        let (value, overflow) = a.overflowing_add(b);
-        self.regs.sp = value;
-
-        self.flags.z = value == 0;
-        self.flags.n = false;
-        self.flags.h = (a << 4).overflowing_add(b << 4).1;
-        self.flags.c = overflow;
+        let offset = self.read_pcaddr8() as i8;
+        self.regs.sp = alu::add_signed8(self.regs.sp, offset, &mut self.regs.f);
+        self.tick();
+        self.tick();
     }
 
     fn sub_a(&mut self, src: &OpsTarget8, carry: &bool) {
-        let a: u8 = self.regs.a;
-        let b: u8 = self.read_opst8(src);
-        let c: u8 = if *carry && self.flags.c { 1 } else { 0 };
-
-        let (tmp, overflow1) = a.overflowing_sub(b);
-        let (value, overflow2) = tmp.overflowing_sub(c);
-        self.regs.a = value;
-
-        let (half_tmp, half_overflow1) = (a << 4).overflowing_sub(b << 4);
-        let half_overflow2: bool = half_tmp.overflowing_sub(c).1;
-
-        self.flags.z = value == 0;
-        self.flags.n = true;
-        self.flags.h = half_overflow1 || half_overflow2;
-        self.flags.c = overflow1 || overflow2;
+        let a = self.regs.a;
+        let b = self.read_opst8(src);
+        let c = *carry && self.regs.f.c();
+        self.regs.a = alu::sub(a, b, c, &mut self.regs.f);
     }
 
     fn and_a(&mut self, src: &OpsTarget8) {
-        let value = self.regs.a & self.read_opst8(src);
-        self.regs.a = value;
-
-        self.flags.z = value == 0;
-        self.flags.n = false;
-        self.flags.h = true;
-        self.flags.c = false;
+        let b = self.read_opst8(src);
+        self.regs.a = alu::and(self.regs.a, b, &mut self.regs.f);
     }
 
     fn xor_a(&mut self, src: &OpsTarget8) {
-        let value = self.regs.a ^ self.read_opst8(src);
-        self.regs.a = value;
-
-        self.flags.z = value == 0;
-        self.flags.n = false;
-        self.flags.h = false;
-        self.flags.c = false;
+        let b = self.read_opst8(src);
+        self.regs.a = alu::xor(self.regs.a, b, &mut self.regs.f);
     }
 
     fn or_a(&mut self, src: &OpsTarget8) {
-        let value = self.regs.a | self.read_opst8(src);
-        self.regs.a = value;
-
-        self.flags.z = value == 0;
-        self.flags.n = false;
-        self.flags.h = true;
-        self.flags.c = false;
+        let b = self.read_opst8(src);
+        self.regs.a = alu::or(self.regs.a, b, &mut self.regs.f);
     }
 
     fn compare_a(&mut self, src: &OpsTarget8) {
-        let a = self.regs.a;
-        self.sub_a(src, &false);
-        self.regs.a = a;
+        let b = self.read_opst8(src);
+        alu::sub(self.regs.a, b, false, &mut self.regs.f);
     }
 
     fn inc(&mut self, dst: &OpsTarget8) {
-        let a: u8 = self.read_opst8(dst);
-        let value = a.wrapping_add(1);
-        self.write_opst8(dst, value);
-
-        self.flags.z = value == 0;
-        self.flags.n = false;
-        self.flags.h = (a << 4).overflowing_add(1).1;
+        self.read_modify_write(dst, alu::inc);
     }
 
+    // 16 bit increments go through the address unit and leave flags alone
     fn inc16(&mut self, dst: &OpsTarget16) {
-        let a: u16 = self.read_opst16(dst);
-        let value = a.wrapping_add(1);
+        let value = self.read_opst16(dst).wrapping_add(1);
         self.write_opst16(dst, value);
-
-        self.flags.z = value == 0;
-        self.flags.n = false;
-        self.flags.h = (a << 4).overflowing_add(1).1;
+        self.tick();
     }
 
     fn dec(&mut self, dst: &OpsTarget8) {
-        let a: u8 = self.read_opst8(dst);
-        let value = a.wrapping_sub(1);
-        self.write_opst8(dst, value);
-
-        self.flags.z = value == 0;
-        self.flags.n = true;
-        self.flags.h = (a << 4).overflowing_sub(1).1;
+        self.read_modify_write(dst, alu::dec);
     }
 
     fn dec16(&mut self, dst: &OpsTarget16) {
-        let a: u16 = self.read_opst16(dst);
-        let value = a.wrapping_sub(1);
+        let value = self.read_opst16(dst).wrapping_sub(1);
         self.write_opst16(dst, value);
-
-        self.flags.z = value == 0;
-        self.flags.n = true;
-        self.flags.h = (a << 4).overflowing_sub(1).1;
+        self.tick();
     }
 
     fn rotate_left_a(&mut self, carry: &bool) {
-        let a = self.regs.a;
-        let (mut value, overflow) = a.overflowing_shl(1);
-        let c = if *carry { self.flags.c } else { overflow };
-
-        if c { value = value | 1 }
-
-        self.flags.z = value == 0;
-        self.flags.n = false;
-        self.flags.h = false;
-        self.flags.c = c;
-
-        self.regs.a = value
+        self.regs.a = alu::rotate_left_a(self.regs.a, *carry, &mut self.regs.f);
     }
 
     fn rotate_right_a(&mut self, carry: &bool) {
-        let a = self.regs.a;
-        let (mut value, overflow) = a.overflowing_shr(1);
-        let c = if *carry { self.flags.c } else { overflow };
+        self.regs.a = alu::rotate_right_a(self.regs.a, *carry, &mut self.regs.f);
+    }
 
-        if c { value = value | 1 }
+    fn decimal_adjust_a(&mut self) {
+        self.regs.a = alu::decimal_adjust(self.regs.a, &mut self.regs.f);
+    }
 
-        self.flags.z = value == 0;
-        self.flags.n = false;
-        self.flags.h = false;
-        self.flags.c = c;
+    fn complement_a(&mut self) {
+        self.regs.a = alu::complement(self.regs.a, &mut self.regs.f);
+    }
 
-        self.regs.a = value
+    // Reads an operand, applies an alu operation to it and writes the result back
+    fn read_modify_write(&mut self, dst: &OpsTarget8, operation: impl FnOnce(u8, &mut Flags) -> u8) {
+        let a = self.read_opst8(dst);
+        let value = operation(a, &mut self.regs.f);
+        self.write_opst8(dst, value);
     }
 
     fn load(&mut self, dst: &OpsTarget8, src: &OpsTarget8) {
@@ -292,6 +451,8 @@ impl Cpu {
     fn load16(&mut self, dst: &OpsTarget16, src: &OpsTarget16) {
         let value: u16 = self.read_opst16(src);
         self.write_opst16(dst, value);
+        // Register to register copy goes over the 8 bit bus
+        if let (OpsTarget16::R16(_), OpsTarget16::R16(_)) = (dst, src) { self.tick() }
     }
 
     fn load_dst_addr(&mut self, dst: &OpsTarget8, src: &OpsTarget8) {
@@ -307,134 +468,184 @@ impl Cpu {
     }
 
     fn load_sp_add_spaddr8_to_hl(&mut self) {
-        // TODO i8
-        let value: u16 = self.regs.sp + self.read_pcaddr8() as u16;
+        let offset = self.read_pcaddr8() as i8;
+        let value = alu::add_signed8(self.regs.sp, offset, &mut self.regs.f);
         self.regs.hl_w(value);
+        self.tick();
     }
 
     fn push(&mut self, reg16: &Register16) {
-        // Todo simplify stack
         let value: u16 = self.regs.read_reg16(reg16);
-        self.regs.sp -= 2;
-        self.mem.write_addr16(self.regs.sp, value);
+        self.push_stack(value);
     }
 
     fn pop(&mut self, reg16: &Register16) {
-        let value: u16 = self.mem.read_addr16(self.regs.sp);
-        self.regs.sp += 2;
+        let value: u16 = self.pop_stack();
         self.regs.write_reg16(reg16, value)
     }
 
+    fn push_stack(&mut self, value: u16) {
+        // Decrementing sp takes an internal cycle before the writes
+        self.tick();
+        let [low, high] = value.to_le_bytes();
+        self.regs.sp = self.regs.sp.wrapping_sub(1);
+        self.write_addr8(self.regs.sp, high);
+        self.regs.sp = self.regs.sp.wrapping_sub(1);
+        self.write_addr8(self.regs.sp, low);
+    }
+
+    fn pop_stack(&mut self) -> u16 {
+        let value: u16 = self.read_addr16(self.regs.sp);
+        self.regs.sp = self.regs.sp.wrapping_add(2);
+        value
+    }
+
     fn jump(&mut self) {
-        self.pc.value = self.read_pcaddr16()
+        let addr = self.read_pcaddr16();
+        self.jump_to(addr);
     }
 
     fn jump_if(&mut self, flag: &Flag) {
-        if self.flags.flag(flag) {
-            self.jump();
-        } else {
-            self.pc.value += 2
+        // The address is read even if the jump is not taken
+        let addr = self.read_pcaddr16();
+        if self.regs.f.flag(flag) {
+            self.jump_to(addr);
         }
     }
 
+    fn jump_to(&mut self, addr: u16) {
+        self.tick();
+        self.pc.value = addr;
+    }
+
     fn jump_hl(&mut self) {
         self.pc.value = self.regs.hl()
     }
 
     fn jump_reg(&mut self) {
-        let b: i8 = self.read_pcaddr8() as i8;
-        let a: u16 = self.pc.value;
-        let value: u16 = (a as i32 + b as i32) as u16;
-        self.pc.value = value;
+        let offset: i8 = self.read_pcaddr8() as i8;
+        self.jump_relative(offset);
     }
 
     fn jump_reg_if(&mut self, flag: &Flag) {
-        if self.flags.flag(flag) {
-            self.jump_reg()
-        } else {
-            self.pc.value += 1;
+        let offset: i8 = self.read_pcaddr8() as i8;
+        if self.regs.f.flag(flag) {
+            self.jump_relative(offset);
         }
     }
 
+    fn jump_relative(&mut self, offset: i8) {
+        self.tick();
+        self.pc.value = self.pc.value.wrapping_add_signed(offset as i16);
+    }
+
     fn call(&mut self) {
-        // Push current pc value to stack
-        self.regs.sp -= 2;
-        self.mem.write_addr16(self.regs.sp, self.pc.value + 2);
-        self.pc.value = self.read_pcaddr16();
+        let addr = self.read_pcaddr16();
+        self.call_to(addr);
     }
 
     fn call_if(&mut self, flag: &Flag) {
-        if self.flags.flag(flag) {
-            self.call()
-        } else {
-            self.pc.value += 2;
+        let addr = self.read_pcaddr16();
+        if self.regs.f.flag(flag) {
+            self.call_to(addr);
         }
     }
 
+    fn call_to(&mut self, addr: u16) {
+        // Push address of next instruction to stack
+        self.push_stack(self.pc.value);
+        self.pc.value = addr;
+    }
+
     fn ret(&mut self) {
-        // Pop value at top of stack to sp
-        self.pc.value = self.mem.read_addr16(self.regs.sp);
-        self.regs.sp += 2
+        let addr = self.pop_stack();
+        self.jump_to(addr);
     }
 
     fn ret_if(&mut self, flag: &Flag) {
-        // Pop value at top of stack to sp
-        if self.flags.flag(flag) {
+        // Checking the condition takes an internal cycle
+        self.tick();
+        if self.regs.f.flag(flag) {
             self.ret()
-        } else {
-            // TODO increase pc?
         }
     }
 
     fn ret_interrupt(&mut self) {
-        // Todo self.pc = pop
-        // Enable interrupts
+        self.ret();
+        self.ime = true;
     }
 
     fn restart(&mut self, addr: &u16) {
-        // Todo push
+        self.push_stack(self.pc.value);
         self.pc.value = *addr;
     }
 
-    // TODO
-
     fn set_carry_flag(&mut self) {
-        self.flags.c = true;
+        alu::set_carry(true, &mut self.regs.f);
     }
 
     fn complement_carry_flag(&mut self) {
-        self.flags.c = !self.flags.c;
+        alu::set_carry(!self.regs.f.c(), &mut self.regs.f);
     }
 
-    // TODO
+    fn set_interrupts(&mut self, enable: &bool) {
+        if *enable {
+            self.ime_scheduled = true;
+        } else {
+            self.ime = false;
+            self.ime_scheduled = false;
+        }
+    }
 
     fn execute_bit_instruction(&mut self, instruction: &BitInstruction) {
         match instruction {
-            BitInstruction::RotateLeft { .. } => {}
-            BitInstruction::RotateRight { .. } => {}
-            BitInstruction::ShiftLeftArithmetic { .. } => {}
-            BitInstruction::ShiftRightArithmetic { .. } => {}
-            BitInstruction::SwapNibbles { .. } => {}
-            BitInstruction::ShiftRightLogical { .. } => {}
+            BitInstruction::RotateLeft { dst, carry } => self.rotate_left(dst, carry),
+            BitInstruction::RotateRight { dst, carry } => self.rotate_right(dst, carry),
+            BitInstruction::ShiftLeftArithmetic { dst } => self.shift_left_arithmetic(dst),
+            BitInstruction::ShiftRightArithmetic { dst } => self.shift_right_arithmetic(dst),
+            BitInstruction::SwapNibbles { dst } => self.swap_nibbles(dst),
+            BitInstruction::ShiftRightLogical { dst } => self.shift_right_logical(dst),
             BitInstruction::BitTest { src, bit } => self.bit_test(src, bit),
-            BitInstruction::BitReset { .. } => {}
+            BitInstruction::BitReset { dst, bit } => self.bit_reset(dst, bit),
             BitInstruction::BitSet { dst, bit } => self.bit_set(dst, bit)
         }
     }
 
+    fn rotate_left(&mut self, dst: &OpsTarget8, carry: &bool) {
+        self.read_modify_write(dst, |a, f| alu::rotate_left(a, *carry, f));
+    }
+
+    fn rotate_right(&mut self, dst: &OpsTarget8, carry: &bool) {
+        self.read_modify_write(dst, |a, f| alu::rotate_right(a, *carry, f));
+    }
+
+    fn shift_left_arithmetic(&mut self, dst: &OpsTarget8) {
+        self.read_modify_write(dst, alu::shift_left_arithmetic);
+    }
+
+    fn shift_right_arithmetic(&mut self, dst: &OpsTarget8) {
+        self.read_modify_write(dst, alu::shift_right_arithmetic);
+    }
+
+    fn swap_nibbles(&mut self, dst: &OpsTarget8) {
+        self.read_modify_write(dst, alu::swap_nibbles);
+    }
+
+    fn shift_right_logical(&mut self, dst: &OpsTarget8) {
+        self.read_modify_write(dst, alu::shift_right_logical);
+    }
+
     fn bit_test(&mut self, dst: &OpsTarget8, bit: &u8) {
         let a = self.read_opst8(dst);
-        let value = a & (1 << bit);
+        alu::bit_test(a, *bit, &mut self.regs.f);
+    }
 
-        self.flags.z = value == 0;
-        self.flags.n = false;
-        self.flags.h = true;
+    fn bit_reset(&mut self, dst: &OpsTarget8, bit: &u8) {
+        self.read_modify_write(dst, |a, _| a & !(1 << bit));
     }
 
     fn bit_set(&mut self, dst: &OpsTarget8, bit: &u8) {
-        let a = self.read_opst8(dst);
-        let value = a | (1 << bit);
-        self.write_opst8(dst, value);
+        self.read_modify_write(dst, |a, _| a | (1 << bit));
     }
 }
 
@@ -445,8 +656,10 @@ trait MemoryOperations {
     fn read_opst16(&mut self, opstarget: &OpsTarget16) -> u16;
     fn write_opst16(&mut self, opstarget: &OpsTarget16, value: u16);
 
-    fn read_addr8(&self, addr: u16) -> u8;
+    fn read_addr8(&mut self, addr: u16) -> u8;
     fn write_addr8(&mut self, addr: u16, value: u8);
+    fn read_addr16(&mut self, addr: u16) -> u16;
+    fn write_addr16(&mut self, addr: u16, value: u16);
 
     fn read_pcaddr8(&mut self) -> u8;
     fn write_pcaddr8(&mut self, value: u8);
@@ -454,66 +667,102 @@ trait MemoryOperations {
     fn write_pcaddr16(&mut self, value: u16);
 }
 
-impl MemoryOperations for Cpu {
+// Every access goes over the 8 bit bus and takes one m-cycle per byte
+impl<B: Bus> MemoryOperations for Cpu<B> {
     fn read_opst8(&mut self, opst8: &OpsTarget8) -> u8 {
         match opst8 {
-            OpsTarget8::R8(r8) => { self.regs.read_reg8(&r8) }
-            OpsTarget8::R16Addr8(r16) => { self.mem.read_addr8(self.regs.read_reg16(r16)) }
+            OpsTarget8::R8(r8) => { self.regs.read_reg8(r8) }
+            OpsTarget8::R16Addr8(r16) => {
+                let addr = self.regs.read_reg16(r16);
+                self.read_addr8(addr)
+            }
             OpsTarget8::PcAddr8 => { self.read_pcaddr8() }
+            OpsTarget8::PcAddr16Addr8 => {
+                let addr = self.read_pcaddr16();
+                self.read_addr8(addr)
+            }
         }
     }
 
     fn write_opst8(&mut self, opst8: &OpsTarget8, value: u8) {
         match opst8 {
-            OpsTarget8::R8(r8) => { self.regs.write_reg8(&r8, value); }
-            OpsTarget8::R16Addr8(r16) => { self.mem.write_addr8(self.regs.read_reg16(r16), value); }
+            OpsTarget8::R8(r8) => { self.regs.write_reg8(r8, value); }
+            OpsTarget8::R16Addr8(r16) => {
+                let addr = self.regs.read_reg16(r16);
+                self.write_addr8(addr, value);
+            }
             OpsTarget8::PcAddr8 => { self.write_pcaddr8(value) }
+            OpsTarget8::PcAddr16Addr8 => {
+                let addr = self.read_pcaddr16();
+                self.write_addr8(addr, value);
+            }
         }
     }
 
     fn read_opst16(&mut self, opst16: &OpsTarget16) -> u16 {
         match opst16 {
-            OpsTarget16::R16(r16) => { self.regs.read_reg16(&r16) }
-            OpsTarget16::PC => { self.pc.value } // Todo increment?
+            OpsTarget16::R16(r16) => { self.regs.read_reg16(r16) }
             OpsTarget16::PcAddr16 => { self.read_pcaddr16() }
+            OpsTarget16::PcAddr16Addr16 => {
+                let addr = self.read_pcaddr16();
+                self.read_addr16(addr)
+            }
         }
     }
 
     fn write_opst16(&mut self, opst16: &OpsTarget16, value: u16) {
         match opst16 {
-            OpsTarget16::R16(r16) => { self.regs.write_reg16(&r16, value); }
-            OpsTarget16::PC => { self.pc.value = value } // Todo increment?
+            OpsTarget16::R16(r16) => { self.regs.write_reg16(r16, value); }
             OpsTarget16::PcAddr16 => { self.write_pcaddr16(value) }
+            OpsTarget16::PcAddr16Addr16 => {
+                let addr = self.read_pcaddr16();
+                self.write_addr16(addr, value);
+            }
         }
     }
 
-    fn read_addr8(&self, addr: u16) -> u8 {
-        self.mem.read_addr8(addr)
+    fn read_addr8(&mut self, addr: u16) -> u8 {
+        self.tick();
+        self.mem.read8(addr)
     }
 
     fn write_addr8(&mut self, addr: u16, value: u8) {
-        self.mem.write_addr8(addr, value);
+        self.tick();
+        self.mem.write8(addr, value);
+    }
+
+    // Low byte first
+    fn read_addr16(&mut self, addr: u16) -> u16 {
+        let low = self.read_addr8(addr);
+        let high = self.read_addr8(addr.wrapping_add(1));
+        u16::from_le_bytes([low, high])
+    }
+
+    fn write_addr16(&mut self, addr: u16, value: u16) {
+        let [low, high] = value.to_le_bytes();
+        self.write_addr8(addr, low);
+        self.write_addr8(addr.wrapping_add(1), high);
     }
 
     fn read_pcaddr8(&mut self) -> u8 {
-        let v = self.mem.read_addr8(self.pc.value);
-        self.pc.value += 1;
+        let v = self.read_addr8(self.pc.value);
+        self.pc.value = self.pc.value.wrapping_add(1);
         v
     }
 
     fn write_pcaddr8(&mut self, value: u8) {
-        self.mem.write_addr8(self.pc.value, value);
-        self.pc.value += 1;
+        self.write_addr8(self.pc.value, value);
+        self.pc.value = self.pc.value.wrapping_add(1);
     }
 
     fn read_pcaddr16(&mut self) -> u16 {
-        let v = self.mem.read_addr16(self.pc.value);
-        self.pc.value += 2;
+        let v = self.read_addr16(self.pc.value);
+        self.pc.value = self.pc.value.wrapping_add(2);
         v
     }
 
     fn write_pcaddr16(&mut self, value: u16) {
-        self.mem.write_addr16(self.pc.value, value);
-        self.pc.value += 2;
+        self.write_addr16(self.pc.value, value);
+        self.pc.value = self.pc.value.wrapping_add(2);
     }
 }
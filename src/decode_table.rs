@@ -0,0 +1,52 @@
+use std::sync::OnceLock;
+use crate::instructions::{BitInstruction, Instruction, Opcode};
+use crate::opcode_parser::InstructionParser;
+
+// Decoded instruction with its static properties, timing comes from the bus accesses it makes
+#[derive(Debug)]
+pub struct OpcodeDescriptor {
+    pub instruction: Instruction,
+    pub length: u8,
+}
+
+#[derive(Debug)]
+pub struct BitOpcodeDescriptor {
+    pub instruction: BitInstruction,
+}
+
+// Both opcode pages, decoded once so the cpu only needs a table lookup per instruction
+#[derive(Debug)]
+pub struct DecodeTable {
+    main: [Option<OpcodeDescriptor>; 256],
+    cb: [BitOpcodeDescriptor; 256],
+}
+
+static DECODE_TABLE: OnceLock<DecodeTable> = OnceLock::new();
+
+impl DecodeTable {
+    pub fn get() -> &'static DecodeTable {
+        DECODE_TABLE.get_or_init(DecodeTable::new)
+    }
+
+    fn new() -> DecodeTable {
+        let main = std::array::from_fn(|op| {
+            Opcode { value: op as u8 }.to_instruction().map(|instruction| OpcodeDescriptor {
+                length: instruction.length(),
+                instruction,
+            })
+        });
+        let cb = std::array::from_fn(|op| {
+            BitOpcodeDescriptor { instruction: Opcode { value: op as u8 }.to_bit_instruction() }
+        });
+        DecodeTable { main, cb }
+    }
+
+    // None for opcodes the emulator cannot decode
+    pub fn main(&self, opcode: u8) -> Option<&OpcodeDescriptor> {
+        self.main[opcode as usize].as_ref()
+    }
+
+    pub fn cb(&self, opcode: u8) -> &BitOpcodeDescriptor {
+        &self.cb[opcode as usize]
+    }
+}
@@ -0,0 +1,314 @@
+use std::fmt::{Display, Formatter};
+use crate::bus::Bus;
+use crate::decode_table::DecodeTable;
+use crate::flags::Flag;
+use crate::instructions::*;
+use crate::registers::{Register16, Register8};
+
+// A decoded instruction in rgbds syntax, with the bytes it was decoded from
+#[derive(PartialEq, Debug)]
+pub struct Disassembly {
+    pub addr: u16,
+    pub bytes: Vec<u8>,
+    pub text: String,
+}
+
+impl Disassembly {
+    pub fn length(&self) -> u8 {
+        self.bytes.len() as u8
+    }
+}
+
+impl Display for Disassembly {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.text)
+    }
+}
+
+// Operand slots of an instruction, the immediate ones take their value from the bytes after the opcode
+#[derive(Debug)]
+enum Operand {
+    // Registers, conditions and register addresses, written as is
+    Text(&'static str),
+    // Value fixed by the opcode, rst vectors and the byte of an illegal opcode
+    Constant(u8),
+    Bit(u8),
+    Imm8,
+    Imm16,
+    // [n16]
+    Addr16,
+    // [$ff00+n8], written as the full address
+    HighAddr8,
+    // Signed offset of add sp
+    Signed8,
+    // sp+e8 of ld hl
+    SpSigned8,
+    // Relative jump, written as the target address
+    Relative,
+}
+
+// Decodes the instruction at addr, the cb prefix and its opcode count as one instruction.
+// Peeks at the bus, so disassembling never has side effects.
+pub fn disassemble(bus: &impl Bus, addr: u16) -> Disassembly {
+    let table = DecodeTable::get();
+    let opcode = bus.peek(addr);
+
+    let (mnemonic, operands, length) = match table.main(opcode) {
+        Some(descriptor) => match descriptor.instruction {
+            Instruction::NestedInstruction => {
+                let cb_opcode = bus.peek(addr.wrapping_add(1));
+                let (mnemonic, operands) = bit_syntax(&table.cb(cb_opcode).instruction);
+                (mnemonic, operands, descriptor.length)
+            }
+            ref instruction => {
+                let (mnemonic, operands) = syntax(instruction);
+                (mnemonic, operands, descriptor.length)
+            }
+        },
+        None => ("db", vec![Operand::Constant(opcode)], 1),
+    };
+
+    let bytes: Vec<u8> = (0..length as u16).map(|i| bus.peek(addr.wrapping_add(i))).collect();
+    let operands: Vec<String> = operands.iter().map(|operand| format_operand(operand, addr, &bytes)).collect();
+    let text = if operands.is_empty() { mnemonic.to_string() } else { format!("{mnemonic} {}", operands.join(", ")) };
+
+    Disassembly { addr, bytes, text }
+}
+
+// Encodes a single instruction in the syntax produced by disassemble, None if it is not valid
+pub fn assemble(text: &str, addr: u16) -> Option<Vec<u8>> {
+    let text = text.trim().to_lowercase();
+    let (mnemonic, rest) = text.split_once(char::is_whitespace).unwrap_or((text.as_str(), ""));
+    let operands: Vec<String> = if rest.trim().is_empty() {
+        Vec::new()
+    } else {
+        rest.split(',').map(|operand| operand.split_whitespace().collect()).collect()
+    };
+
+    let table = DecodeTable::get();
+    let main = (0..=255u8).filter_map(|opcode| {
+        let descriptor = table.main(opcode)?;
+        match descriptor.instruction {
+            Instruction::NestedInstruction => None,
+            ref instruction => Some((vec![opcode], syntax(instruction), descriptor.length)),
+        }
+    });
+    let cb = (0..=255u8).map(|opcode| (vec![0xCB, opcode], bit_syntax(&table.cb(opcode).instruction), 2));
+
+    main.chain(cb).find_map(|(mut bytes, (candidate, slots), length)| {
+        if candidate != mnemonic || slots.len() != operands.len() { return None; }
+        for (slot, operand) in slots.iter().zip(&operands) {
+            bytes.extend(encode_operand(slot, operand, addr)?);
+        }
+        // Stop is followed by a byte that is not an operand
+        bytes.resize(length as usize, 0);
+        Some(bytes)
+    })
+}
+
+fn syntax(instruction: &Instruction) -> (&'static str, Vec<Operand>) {
+    use Instruction::*;
+    use Operand::*;
+
+    match instruction {
+        Noop => ("nop", vec![]),
+        Stop => ("stop", vec![]),
+        Halt => ("halt", vec![]),
+
+        AddA { src, carry } => (if *carry { "adc" } else { "add" }, vec![Text("a"), operand8(src)]),
+        AddHl { src } => ("add", vec![Text("hl"), Text(register16_name(src))]),
+        AddSpAddr8ToSp => ("add", vec![Text("sp"), Signed8]),
+        SubA { src, carry } => (if *carry { "sbc" } else { "sub" }, vec![Text("a"), operand8(src)]),
+        AndA { src } => ("and", vec![Text("a"), operand8(src)]),
+        XorA { src } => ("xor", vec![Text("a"), operand8(src)]),
+        OrA { src } => ("or", vec![Text("a"), operand8(src)]),
+        CompareA { src } => ("cp", vec![Text("a"), operand8(src)]),
+        Inc { dst } => ("inc", vec![operand8(dst)]),
+        Inc16 { dst } => ("inc", vec![operand16(dst)]),
+        Dec { dst } => ("dec", vec![operand8(dst)]),
+        Dec16 { dst } => ("dec", vec![operand16(dst)]),
+        RotateLeftA { carry } => (if *carry { "rla" } else { "rlca" }, vec![]),
+        RotateRightA { carry } => (if *carry { "rra" } else { "rrca" }, vec![]),
+
+        Load { dst, src } => ("ld", vec![operand8(dst), operand8(src)]),
+        Load16 { dst, src } => ("ld", vec![operand16(dst), operand16(src)]),
+        LoadDstAddr { dst, src } => ("ldh", vec![high_operand8(dst), operand8(src)]),
+        LoadSrcAddr { dst, src } => ("ldh", vec![operand8(dst), high_operand8(src)]),
+        LoadSpAddSpAddr8ToHl => ("ld", vec![Text("hl"), SpSigned8]),
+
+        Push { reg16 } => ("push", vec![Text(register16_name(reg16))]),
+        Pop { reg16 } => ("pop", vec![Text(register16_name(reg16))]),
+        Jump => ("jp", vec![Imm16]),
+        JumpIf { flag } => ("jp", vec![Text(condition_name(flag)), Imm16]),
+        JumpHL => ("jp", vec![Text("hl")]),
+        JumpReg => ("jr", vec![Relative]),
+        JumpRegIf { flag } => ("jr", vec![Text(condition_name(flag)), Relative]),
+        Call => ("call", vec![Imm16]),
+        CallIf { flag } => ("call", vec![Text(condition_name(flag)), Imm16]),
+        Return => ("ret", vec![]),
+        ReturnIf { flag } => ("ret", vec![Text(condition_name(flag))]),
+        ReturnInterrupt => ("reti", vec![]),
+        Restart { addr } => ("rst", vec![Constant(*addr as u8)]),
+
+        DecimalAdjustA => ("daa", vec![]),
+        ComplementA => ("cpl", vec![]),
+        SetCarryFlag => ("scf", vec![]),
+        ComplementCarryFlag => ("ccf", vec![]),
+        SetInterrupts { enable } => (if *enable { "ei" } else { "di" }, vec![]),
+
+        // Only reached through the cb page
+        NestedInstruction => ("prefix", vec![]),
+        Illegal { opcode } => ("db", vec![Constant(*opcode)]),
+    }
+}
+
+fn bit_syntax(instruction: &BitInstruction) -> (&'static str, Vec<Operand>) {
+    use BitInstruction::*;
+
+    match instruction {
+        RotateLeft { dst, carry } => (if *carry { "rl" } else { "rlc" }, vec![operand8(dst)]),
+        RotateRight { dst, carry } => (if *carry { "rr" } else { "rrc" }, vec![operand8(dst)]),
+        ShiftLeftArithmetic { dst } => ("sla", vec![operand8(dst)]),
+        ShiftRightArithmetic { dst } => ("sra", vec![operand8(dst)]),
+        SwapNibbles { dst } => ("swap", vec![operand8(dst)]),
+        ShiftRightLogical { dst } => ("srl", vec![operand8(dst)]),
+        BitTest { src, bit } => ("bit", vec![Operand::Bit(*bit), operand8(src)]),
+        BitReset { dst, bit } => ("res", vec![Operand::Bit(*bit), operand8(dst)]),
+        BitSet { dst, bit } => ("set", vec![Operand::Bit(*bit), operand8(dst)]),
+    }
+}
+
+fn operand8(target: &OpsTarget8) -> Operand {
+    match target {
+        OpsTarget8::R8(r8) => Operand::Text(register8_name(r8)),
+        OpsTarget8::R16Addr8(r16) => Operand::Text(match r16 {
+            Register16::BC => "[bc]",
+            Register16::DE => "[de]",
+            Register16::HLI => "[hl+]",
+            Register16::HLD => "[hl-]",
+            _ => "[hl]",
+        }),
+        OpsTarget8::PcAddr8 => Operand::Imm8,
+        OpsTarget8::PcAddr16Addr8 => Operand::Addr16,
+    }
+}
+
+// The address side of ldh, relative to 0xFF00
+fn high_operand8(target: &OpsTarget8) -> Operand {
+    match target {
+        OpsTarget8::PcAddr8 => Operand::HighAddr8,
+        _ => Operand::Text("[c]"),
+    }
+}
+
+fn operand16(target: &OpsTarget16) -> Operand {
+    match target {
+        OpsTarget16::R16(r16) => Operand::Text(register16_name(r16)),
+        OpsTarget16::PcAddr16 => Operand::Imm16,
+        OpsTarget16::PcAddr16Addr16 => Operand::Addr16,
+    }
+}
+
+fn register8_name(register: &Register8) -> &'static str {
+    match register {
+        Register8::A => "a",
+        Register8::F => "f",
+        Register8::B => "b",
+        Register8::C => "c",
+        Register8::D => "d",
+        Register8::E => "e",
+        Register8::H => "h",
+        Register8::L => "l",
+    }
+}
+
+fn register16_name(register: &Register16) -> &'static str {
+    match register {
+        Register16::AF => "af",
+        Register16::BC => "bc",
+        Register16::DE => "de",
+        Register16::HL | Register16::HLI | Register16::HLD => "hl",
+        Register16::SP => "sp",
+    }
+}
+
+fn condition_name(flag: &Flag) -> &'static str {
+    match flag {
+        Flag::Z => "z",
+        Flag::NZ => "nz",
+        Flag::C => "c",
+        Flag::NC => "nc",
+    }
+}
+
+fn format_operand(operand: &Operand, addr: u16, bytes: &[u8]) -> String {
+    let imm8 = || bytes[1];
+    let imm16 = || u16::from_le_bytes([bytes[1], bytes[2]]);
+    let signed = |value: i8| if value < 0 { format!("-${:02x}", value.unsigned_abs()) } else { format!("${value:02x}") };
+
+    match operand {
+        Operand::Text(text) => text.to_string(),
+        Operand::Constant(value) => format!("${value:02x}"),
+        Operand::Bit(bit) => bit.to_string(),
+        Operand::Imm8 => format!("${:02x}", imm8()),
+        Operand::Imm16 => format!("${:04x}", imm16()),
+        Operand::Addr16 => format!("[${:04x}]", imm16()),
+        Operand::HighAddr8 => format!("[${:04x}]", 0xFF00 | imm8() as u16),
+        Operand::Signed8 => signed(imm8() as i8),
+        Operand::SpSigned8 => {
+            let offset = imm8() as i8;
+            if offset < 0 { format!("sp{}", signed(offset)) } else { format!("sp+{}", signed(offset)) }
+        }
+        Operand::Relative => {
+            let target = addr.wrapping_add(2).wrapping_add_signed(imm8() as i8 as i16);
+            format!("${target:04x}")
+        }
+    }
+}
+
+// Immediate bytes for an operand, None if the text does not fit the slot
+fn encode_operand(slot: &Operand, text: &str, addr: u16) -> Option<Vec<u8>> {
+    let byte = |value: i32| (-0x80..=0xFF).contains(&value).then(|| vec![value as u8]);
+    let word = |value: i32| (-0x8000..=0xFFFF).contains(&value).then(|| (value as u16).to_le_bytes().to_vec());
+    let signed = |value: i32| (-0x80..=0x7F).contains(&value).then(|| vec![value as u8]);
+
+    match slot {
+        Operand::Text(expected) => (*expected == text).then(Vec::new),
+        Operand::Constant(value) => (parse_number(text)? == *value as i32).then(Vec::new),
+        Operand::Bit(bit) => (parse_number(text)? == *bit as i32).then(Vec::new),
+        Operand::Imm8 => byte(parse_number(text)?),
+        Operand::Imm16 => word(parse_number(text)?),
+        Operand::Addr16 => word(parse_number(strip_brackets(text)?)?),
+        Operand::HighAddr8 => {
+            let value = parse_number(strip_brackets(text)?)?;
+            (0xFF00..=0xFFFF).contains(&value).then(|| vec![value as u8])
+        }
+        Operand::Signed8 => signed(parse_number(text)?),
+        Operand::SpSigned8 => {
+            let offset = text.strip_prefix("sp")?;
+            signed(parse_number(offset.strip_prefix('+').unwrap_or(offset))?)
+        }
+        Operand::Relative => {
+            let target = parse_number(text)?;
+            if !(0..=0xFFFF).contains(&target) { return None; }
+            signed((target as u16).wrapping_sub(addr.wrapping_add(2)) as i16 as i32)
+        }
+    }
+}
+
+fn strip_brackets(text: &str) -> Option<&str> {
+    text.strip_prefix('[')?.strip_suffix(']')
+}
+
+// $ for hexadecimal, decimal otherwise, with an optional minus sign
+fn parse_number(text: &str) -> Option<i32> {
+    let (negative, digits) = match text.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, text),
+    };
+    let value = match digits.strip_prefix('$') {
+        Some(hex) => i32::from_str_radix(hex, 16).ok()?,
+        None => digits.parse().ok()?,
+    };
+    Some(if negative { -value } else { value })
+}
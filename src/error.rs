@@ -0,0 +1,43 @@
+use std::fmt::{Display, Formatter};
+
+// Cpu state at the point an error was raised
+#[derive(PartialEq, Clone, Debug)]
+pub struct ErrorContext {
+    pub pc: u16,
+    pub opcode: u8,
+    pub bank: u16,
+    pub cycles: u64,
+}
+
+impl Display for ErrorContext {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "opcode {:#04x} at {:#06x}, rom bank {}, after {} cycles", self.opcode, self.pc, self.bank, self.cycles)
+    }
+}
+
+#[derive(PartialEq, Clone, Debug)]
+pub enum EmuError {
+    // Decoded, but not supported by the emulator yet
+    UnimplementedOpcode(ErrorContext),
+    // Locked up the cpu, only raised in strict mode
+    IllegalOpcode(ErrorContext),
+    // Raised while loading, before any instruction has run
+    InvalidRomHeader { reason: String },
+    InvalidBootRom { size: usize },
+    // Access the program should never make, only raised in strict mode
+    BusFault { addr: u16, context: ErrorContext },
+}
+
+impl Display for EmuError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EmuError::UnimplementedOpcode(context) => write!(f, "Unimplemented {context}"),
+            EmuError::IllegalOpcode(context) => write!(f, "Illegal {context}"),
+            EmuError::InvalidRomHeader { reason } => write!(f, "Invalid rom: {reason}"),
+            EmuError::InvalidBootRom { size } => write!(f, "Invalid boot rom: {size} bytes, expected 256 or 2304"),
+            EmuError::BusFault { addr, context } => write!(f, "Bus fault at {addr:#06x} executing {context}"),
+        }
+    }
+}
+
+impl std::error::Error for EmuError {}
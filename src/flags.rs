@@ -1,33 +1,67 @@
+use std::fmt::{Debug, Formatter};
+
 #[derive(PartialEq, Debug)]
 pub enum Flag {
     Z, NZ, C, NC
 }
 
-#[derive(Default, Debug)]
+// View over the F register, flags are bits 7..4 and the low nibble is always zero
+#[derive(Default, Clone, Copy, PartialEq)]
 pub struct Flags {
+    bits: u8
+}
+
+impl Debug for Flags {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Flags {{ z: {}, n: {}, h: {}, c: {} }}", self.z(), self.n(), self.h(), self.c())
+    }
+}
+
+impl Flags {
     // Zero
-    pub z: bool,
+    const Z: u8 = 1 << 7;
     // Negative
-    pub n: bool,
+    const N: u8 = 1 << 6;
     // Half carry
-    pub h: bool,
+    const H: u8 = 1 << 5;
     // Carry
-    pub c: bool
-}
-
-impl Flags {
+    const C: u8 = 1 << 4;
 
     pub fn new() -> Self {
         Default::default()
     }
 
+    pub fn from_bits(value: u8) -> Self {
+        Flags { bits: value & 0xF0 }
+    }
+
+    pub fn bits(&self) -> u8 { self.bits }
+
+    pub fn z(&self) -> bool { self.get(Flags::Z) }
+    pub fn n(&self) -> bool { self.get(Flags::N) }
+    pub fn h(&self) -> bool { self.get(Flags::H) }
+    pub fn c(&self) -> bool { self.get(Flags::C) }
+
+    pub fn set_z(&mut self, value: bool) { self.set(Flags::Z, value) }
+    pub fn set_n(&mut self, value: bool) { self.set(Flags::N, value) }
+    pub fn set_h(&mut self, value: bool) { self.set(Flags::H, value) }
+    pub fn set_c(&mut self, value: bool) { self.set(Flags::C, value) }
+
     // Flags useful for branching
     pub fn flag(&self, flag: &Flag) -> bool {
         match flag {
-            Flag::Z => self.z,
-            Flag::NZ => !self.z,
-            Flag::C => self.c,
-            Flag::NC => !self.c
+            Flag::Z => self.z(),
+            Flag::NZ => !self.z(),
+            Flag::C => self.c(),
+            Flag::NC => !self.c()
         }
     }
-}
\ No newline at end of file
+
+    fn get(&self, mask: u8) -> bool {
+        self.bits & mask != 0
+    }
+
+    fn set(&mut self, mask: u8, value: bool) {
+        if value { self.bits |= mask } else { self.bits &= !mask }
+    }
+}
@@ -1,15 +1,30 @@
-use minifb::{Scale, Window, WindowOptions};
+use minifb::{Key, Scale, Window, WindowOptions};
 use crate::gpu::{WIDTH, HEIGHT, Gpu};
+use crate::joypad::Button;
+use crate::memory::Memory;
+
+const KEY_MAP: [(Key, Button); 8] = [
+    (Key::Right, Button::Right),
+    (Key::Left, Button::Left),
+    (Key::Up, Button::Up),
+    (Key::Down, Button::Down),
+    (Key::Z, Button::A),
+    (Key::X, Button::B),
+    (Key::Backspace, Button::Select),
+    (Key::Enter, Button::Start),
+];
 
 pub struct Frontend {
     window: Window,
     buffer: [u32;WIDTH*HEIGHT],
+    // Set while the cartridge runs its rumble motor, the picture shakes
+    rumbling: bool,
+    shake_left: bool,
 }
 
 impl Frontend {
     pub fn new() -> Frontend {
-        let mut options = WindowOptions::default();
-        options.scale = Scale::X4;
+        let options = WindowOptions { scale: Scale::X4, ..WindowOptions::default() };
 
         let mut window = Window::new(
             "rgbc",
@@ -29,24 +44,53 @@ impl Frontend {
         window.update_with_buffer(&buffer, WIDTH, HEIGHT)
             .unwrap();
 
-        Frontend { window, buffer }
+        Frontend { window, buffer, rumbling: false, shake_left: false }
     }
 
-    pub fn step(&mut self, gpu: &Gpu) {
+    pub fn step(&mut self, gpu: &Gpu, mem: &mut Memory) {
+        if let Some(rumbling) = mem.cartridge.take_rumble() {
+            self.rumbling = rumbling;
+        }
         if self.window.is_open() && gpu.dirty {
             self.draw_buffer(gpu);
+            self.read_input(mem);
         }
     }
 
+    // Keeps polling input while nothing is drawn, e.g. while the cpu is stopped
+    pub fn idle(&mut self, mem: &mut Memory) {
+        self.window.update();
+        self.read_input(mem);
+    }
+
     pub fn is_open(&self) -> bool {
         self.window.is_open()
     }
 
     fn draw_buffer(&mut self, gpu: &Gpu) {
         self.buffer.copy_from_slice(&gpu.buffer);
+        if self.rumbling {
+            // Shifts the picture two pixels left and right on alternate frames
+            self.shake_left = !self.shake_left;
+            for row in self.buffer.chunks_mut(WIDTH) {
+                if self.shake_left { row.rotate_left(2) } else { row.rotate_right(2) }
+            }
+        }
 
         self.window
             .update_with_buffer(&self.buffer, WIDTH, HEIGHT)
             .unwrap();
     }
-}
\ No newline at end of file
+
+    fn read_input(&self, mem: &mut Memory) {
+        for (key, button) in KEY_MAP {
+            mem.set_button(button, self.window.is_key_down(key));
+        }
+    }
+}
+
+impl Default for Frontend {
+    fn default() -> Self {
+        Frontend::new()
+    }
+}
@@ -1,4 +1,4 @@
-use crate::Memory;
+use crate::memory::Memory;
 
 pub const WIDTH: usize = 160;
 pub const HEIGHT: usize = 144;
@@ -11,6 +11,7 @@ pub struct Gpu {
     pub dirty: bool,
 }
 
+#[allow(dead_code)]
 struct Sprite {
     y: u8,
     x: u8,
@@ -30,19 +31,17 @@ impl Gpu {
     fn read_tile(&self, mem: &Memory, tile_index: usize) -> Tile {
         let mut tile: Tile = [[0; TILE_SIDE]; TILE_SIDE];
 
-        let lcd = mem.data[0xFF44];
-
         // let addr: usize = if lcd & 1 << 7 == 0 { 0x8000 + tile_index } else { 0x8000 as usize };
-        let addr: usize = 0x8000 + tile_index * 16 as usize;
+        let addr: usize = 0x8000 + tile_index * 16;
 
         // For each tile row
-        for i in 0..TILE_SIDE {
+        for (i, row) in tile.iter_mut().enumerate() {
             // There are two bytes
-            let byte1: u8 = mem.data[addr + i * 2];
-            let byte2: u8 = mem.data[addr + i * 2 + 1];
+            let byte1: u8 = mem.read_addr8((addr + i * 2) as u16);
+            let byte2: u8 = mem.read_addr8((addr + i * 2 + 1) as u16);
 
             // For each pixel
-            for p in 0..TILE_SIDE {
+            for (p, pixel) in row.iter_mut().enumerate() {
                 // Consists of two bits from each byte
                 let bit1 = (byte1 >> (7 - p)) & 1;
                 let bit2 = (byte2 >> (7 - p)) & 1;
@@ -50,14 +49,14 @@ impl Gpu {
                 // Final color is two bits color depth, as index to palette
                 let c = bit2 << 1 | bit1;
 
-                tile[i][p] = c
+                *pixel = c
             }
         }
         tile
     }
 
     pub fn step(&mut self, mem: &Memory) {
-        let scanline: u8 = mem.data[0xFF44];
+        let scanline: u8 = mem.ly();
         self.dirty = false;
         if self.scanline != scanline && scanline as usize == HEIGHT {
             self.buffer.fill(0xFFFFFF);
@@ -72,31 +71,29 @@ impl Gpu {
     fn draw_tiles(&mut self, mem: &Memory) {
         // TODO lcd
         let mut tilemap_index: usize = 0;
-        let scroll_y: usize = mem.data[0xFF42] as usize;
+        let scroll_y: usize = mem.read_addr8(0xFF42) as usize;
 
         let screen_tile_cols = 20;
         let screen_tile_rows = 18;
         let tilemap_side = 32;
 
-        println!("Scroll y: {scroll_y}");
         while tilemap_index < screen_tile_cols * screen_tile_rows {
             let tilemap_y_offset: usize = (tilemap_index / screen_tile_cols) * tilemap_side;
             let tilemap_x_offset: usize = tilemap_index % screen_tile_cols;
-            let tile_index: usize = mem.data[0x9800 + tilemap_y_offset + tilemap_x_offset] as usize;
+            let tile_index: usize = mem.read_addr8((0x9800 + tilemap_y_offset + tilemap_x_offset) as u16) as usize;
 
             let tile: Tile = self.read_tile(mem, tile_index);
             let tile_pos_y: i16 = ((tilemap_index / screen_tile_cols) * TILE_SIDE) as i16 - scroll_y as i16;
             let tile_pos_x: usize = tilemap_x_offset * TILE_SIDE;
 
-            for tile_pixel_y in 0..TILE_SIDE {
-                for tile_pixel_x in 0..TILE_SIDE {
+            for (tile_pixel_y, row) in tile.iter().enumerate() {
+                for (tile_pixel_x, c) in row.iter().enumerate() {
                     let y: i16 = tile_pos_y + tile_pixel_y as i16;
                     let x: usize = tile_pos_x + tile_pixel_x;
 
                     if y < 0 || y as usize >= HEIGHT || x >= WIDTH { continue; }
 
-                    let c = tile[tile_pixel_y][tile_pixel_x];
-                    self.buffer[y as usize * WIDTH + x as usize] = TILE_COLORS[c as usize];
+                    self.buffer[y as usize * WIDTH + x] = TILE_COLORS[*c as usize];
                 }
             }
 
@@ -104,13 +101,14 @@ impl Gpu {
         }
     }
 
+    #[allow(dead_code)]
     fn draw_sprites(&mut self, mem: &Memory) {
         let mut addr: usize = 0xFE00;
         while addr <= 0xFE9F {
-            let tile_y: i16 = mem.data[addr] as i16 - 16;
-            let tile_x: i16 = mem.data[addr + 1] as i16 - 8;
-            let tile_index: usize = mem.data[addr + 3] as usize;
-            // let flags = mem.data[addr + 3];
+            let tile_y: i16 = mem.read_addr8(addr as u16) as i16 - 16;
+            let tile_x: i16 = mem.read_addr8((addr + 1) as u16) as i16 - 8;
+            let tile_index: usize = mem.read_addr8((addr + 3) as u16) as usize;
+            // let flags = mem.read_addr8((addr + 3) as u16);
 
             if tile_index == 0 {
                 addr += 4;
@@ -119,13 +117,12 @@ impl Gpu {
 
             let tile = self.read_tile(mem, tile_index);
 
-            for tile_pixel_y in 0..TILE_SIDE {
-                for tile_pixel_x in 0..TILE_SIDE {
+            for (tile_pixel_y, row) in tile.iter().enumerate() {
+                for (tile_pixel_x, c) in row.iter().enumerate() {
                     let y = tile_y + tile_pixel_y as i16;
                     let x = tile_x + tile_pixel_x as i16;
                     if y < 0 || x < 0 || y as usize >= HEIGHT || x as usize >= WIDTH { continue; };
-                    let c = tile[tile_pixel_y][tile_pixel_x];
-                    self.buffer[y as usize * WIDTH + x as usize] = TILE_COLORS[c as usize];
+                    self.buffer[y as usize * WIDTH + x as usize] = TILE_COLORS[*c as usize];
                 }
             }
 
@@ -133,6 +130,7 @@ impl Gpu {
         }
     }
 
+    #[allow(dead_code)]
     fn draw_tilemap(&mut self, mem: &Memory) {
         self.buffer.fill(0xFFFFFF);
         let bytes_per_tile = 16;
@@ -144,16 +142,21 @@ impl Gpu {
 
             let tile: Tile = self.read_tile(mem, tile_index);
 
-            for pixel_y in 0..TILE_SIDE {
-                for pixel_x in 0..TILE_SIDE {
+            for (pixel_y, row) in tile.iter().enumerate() {
+                for (pixel_x, c) in row.iter().enumerate() {
                     let y = buffer_y + pixel_y;
                     let x = buffer_x + pixel_x;
-                    let c = tile[pixel_y][pixel_x];
-                    self.buffer[y * WIDTH + x] = TILE_COLORS[c as usize];
+                    self.buffer[y * WIDTH + x] = TILE_COLORS[*c as usize];
                 }
             }
 
             tile_index += 1;
         }
     }
+}
+
+impl Default for Gpu {
+    fn default() -> Self {
+        Gpu::new()
+    }
 }
\ No newline at end of file
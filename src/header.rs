@@ -0,0 +1,119 @@
+use crate::error::EmuError;
+use crate::model::Model;
+
+// Logo the boot rom compares against before starting a cartridge
+pub const NINTENDO_LOGO: [u8; 48] = [
+    0xCE, 0xED, 0x66, 0x66, 0xCC, 0x0D, 0x00, 0x0B, 0x03, 0x73, 0x00, 0x83, 0x00, 0x0C, 0x00, 0x0D,
+    0x00, 0x08, 0x11, 0x1F, 0x88, 0x89, 0x00, 0x0E, 0xDC, 0xCC, 0x6E, 0xE6, 0xDD, 0xDD, 0xD9, 0x99,
+    0xBB, 0xBB, 0x67, 0x63, 0x6E, 0x0E, 0xEC, 0xCC, 0xDD, 0xDC, 0x99, 0x9F, 0xBB, 0xB9, 0x33, 0x3E,
+];
+
+// Bytes 0x0100-0x014F of every cartridge, see the Pan Docs cartridge header
+#[derive(PartialEq, Clone, Debug)]
+pub struct CartridgeHeader {
+    pub title: String,
+    // Four letters on newer cartridges, empty when those bytes still belong to the title
+    pub manufacturer: String,
+    pub cgb_flag: u8,
+    pub sgb_flag: u8,
+    pub cartridge_type: u8,
+    pub rom_size_code: u8,
+    pub ram_size_code: u8,
+    // 0x00 for Japan, 0x01 for everywhere else
+    pub destination: u8,
+    pub version: u8,
+    pub header_checksum: u8,
+    pub global_checksum: u16,
+    computed_header_checksum: u8,
+    computed_global_checksum: u16,
+}
+
+impl CartridgeHeader {
+    pub fn parse(rom: &[u8]) -> Result<CartridgeHeader, EmuError> {
+        if rom.len() < 0x150 {
+            let reason = format!("rom is {} bytes, too short for a cartridge header", rom.len());
+            return Err(EmuError::InvalidRomHeader { reason });
+        }
+
+        // The cgb flag and the manufacturer code took over the last bytes of the title
+        let cgb_flag = rom[0x0143];
+        let (title, manufacturer) = if cgb_flag & 0x80 != 0 {
+            (text(&rom[0x0134..0x013F]), text(&rom[0x013F..0x0143]))
+        } else {
+            (text(&rom[0x0134..0x0144]), String::new())
+        };
+
+        let header = CartridgeHeader {
+            title,
+            manufacturer,
+            cgb_flag,
+            sgb_flag: rom[0x0146],
+            cartridge_type: rom[0x0147],
+            rom_size_code: rom[0x0148],
+            ram_size_code: rom[0x0149],
+            destination: rom[0x014A],
+            version: rom[0x014C],
+            header_checksum: rom[0x014D],
+            global_checksum: u16::from_be_bytes([rom[0x014E], rom[0x014F]]),
+            computed_header_checksum: rom[0x0134..=0x014C].iter().fold(0u8, |sum, byte| sum.wrapping_sub(*byte).wrapping_sub(1)),
+            // Every byte except the global checksum itself
+            computed_global_checksum: rom.iter().enumerate()
+                .filter(|(i, _)| !(0x014E..=0x014F).contains(i))
+                .fold(0u16, |sum, (_, byte)| sum.wrapping_add(*byte as u16)),
+        };
+
+        if let Some(size) = header.rom_size() {
+            if rom.len() < size {
+                let reason = format!("rom is {} bytes, the header declares {size}", rom.len());
+                return Err(EmuError::InvalidRomHeader { reason });
+            }
+        }
+        Ok(header)
+    }
+
+    // The boot rom locks up when this does not match
+    pub fn is_header_checksum_valid(&self) -> bool {
+        self.header_checksum == self.computed_header_checksum
+    }
+
+    // Never checked by the hardware
+    pub fn is_global_checksum_valid(&self) -> bool {
+        self.global_checksum == self.computed_global_checksum
+    }
+
+    // 32 KiB doubled for every step, None for unknown codes
+    pub fn rom_size(&self) -> Option<usize> {
+        (self.rom_size_code <= 8).then(|| 0x8000 << self.rom_size_code)
+    }
+
+    pub fn ram_size(&self) -> Option<usize> {
+        match self.ram_size_code {
+            0x00 => Some(0),
+            // Unofficial, never used by a released cartridge
+            0x01 => Some(0x800),
+            0x02 => Some(0x2000),
+            0x03 => Some(0x8000),
+            0x04 => Some(0x20000),
+            0x05 => Some(0x10000),
+            _ => None,
+        }
+    }
+
+    // Ram kept alive by a battery, saved between sessions
+    pub fn has_battery(&self) -> bool {
+        matches!(self.cartridge_type, 0x03 | 0x06 | 0x09 | 0x0D | 0x0F | 0x10 | 0x13 | 0x1B | 0x1E | 0x22 | 0xFF)
+    }
+
+    // Cartridges with cgb features run on a cgb, everything else on a dmg
+    pub fn model(&self) -> Model {
+        if self.cgb_flag & 0x80 != 0 { Model::Cgb } else { Model::Dmg }
+    }
+}
+
+// Ascii up to the first zero byte
+fn text(bytes: &[u8]) -> String {
+    bytes.iter()
+        .take_while(|byte| **byte != 0)
+        .map(|byte| if byte.is_ascii_graphic() || *byte == b' ' { *byte as char } else { '?' })
+        .collect()
+}
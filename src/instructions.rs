@@ -13,6 +13,7 @@ impl Debug for Opcode {
 }
 
 #[derive(Debug)]
+#[allow(clippy::enum_variant_names)]
 pub enum Instruction {
     Noop,
     Stop,
@@ -60,6 +61,8 @@ pub enum Instruction {
     SetInterrupts { enable: bool },
 
     NestedInstruction,
+    // Unused opcode, locks up the cpu
+    Illegal { opcode: u8 },
 }
 
 #[derive(Debug)]
@@ -80,11 +83,14 @@ pub enum OpsTarget8 {
     R8(Register8),
     R16Addr8(Register16),
     PcAddr8,
+    // Byte at the address given by the 16 bit immediate
+    PcAddr16Addr8,
 }
 
 #[derive(PartialEq, Debug)]
 pub enum OpsTarget16 {
     R16(Register16),
-    PC,
     PcAddr16,
+    // Word at the address given by the 16 bit immediate
+    PcAddr16Addr16,
 }
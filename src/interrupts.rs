@@ -0,0 +1,76 @@
+pub const IF_ADDR: u16 = 0xFF0F;
+pub const IE_ADDR: u16 = 0xFFFF;
+
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum Interrupt {
+    VBlank,
+    LcdStat,
+    Timer,
+    Serial,
+    Joypad,
+}
+
+impl Interrupt {
+    // Ordered by priority, highest first
+    pub const ALL: [Interrupt; 5] = [
+        Interrupt::VBlank,
+        Interrupt::LcdStat,
+        Interrupt::Timer,
+        Interrupt::Serial,
+        Interrupt::Joypad,
+    ];
+
+    fn index(&self) -> u8 {
+        match self {
+            Interrupt::VBlank => 0,
+            Interrupt::LcdStat => 1,
+            Interrupt::Timer => 2,
+            Interrupt::Serial => 3,
+            Interrupt::Joypad => 4,
+        }
+    }
+
+    // Bit in both IE and IF
+    pub fn bit(&self) -> u8 {
+        1 << self.index()
+    }
+
+    // Address the cpu jumps to when servicing the interrupt
+    pub fn vector(&self) -> u16 {
+        0x40 + self.index() as u16 * 8
+    }
+}
+
+// The IE and IF registers, shared by the cpu and every interrupt source
+#[derive(Default, Debug)]
+pub struct Interrupts {
+    enable: u8,
+    flags: u8,
+}
+
+impl Interrupts {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn request(&mut self, interrupt: Interrupt) {
+        self.flags |= interrupt.bit();
+    }
+
+    pub fn acknowledge(&mut self, interrupt: Interrupt) {
+        self.flags &= !interrupt.bit();
+    }
+
+    // Highest priority interrupt that is both requested and enabled
+    pub fn pending(&self) -> Option<Interrupt> {
+        let active = self.enable & self.flags;
+        Interrupt::ALL.into_iter().find(|interrupt| active & interrupt.bit() != 0)
+    }
+
+    pub fn read_ie(&self) -> u8 { self.enable }
+    pub fn write_ie(&mut self, value: u8) { self.enable = value }
+
+    // Only the low 5 bits exist, the rest read as 1
+    pub fn read_if(&self) -> u8 { self.flags | 0xE0 }
+    pub fn write_if(&mut self, value: u8) { self.flags = value & 0x1F }
+}
@@ -0,0 +1,79 @@
+pub const P1_ADDR: u16 = 0xFF00;
+
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum Button {
+    // Direction buttons
+    Right,
+    Left,
+    Up,
+    Down,
+    // Action buttons
+    A,
+    B,
+    Select,
+    Start,
+}
+
+impl Button {
+    // Bit in the pressed mask, low nibble directions and high nibble actions
+    fn bit(&self) -> u8 {
+        match self {
+            Button::Right => 1 << 0,
+            Button::Left => 1 << 1,
+            Button::Up => 1 << 2,
+            Button::Down => 1 << 3,
+            Button::A => 1 << 4,
+            Button::B => 1 << 5,
+            Button::Select => 1 << 6,
+            Button::Start => 1 << 7,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Joypad {
+    // Set bits are pressed buttons
+    pressed: u8,
+    // P1 bits 4 and 5, a cleared bit selects directions or actions respectively
+    select: u8,
+}
+
+impl Joypad {
+    pub fn new() -> Self {
+        Joypad { pressed: 0, select: 0x30 }
+    }
+
+    // Returns true if the button went from released to pressed
+    pub fn set_pressed(&mut self, button: Button, pressed: bool) -> bool {
+        let was_pressed = self.pressed & button.bit() != 0;
+        if pressed {
+            self.pressed |= button.bit();
+        } else {
+            self.pressed &= !button.bit();
+        }
+        pressed && !was_pressed
+    }
+
+    pub fn any_pressed(&self) -> bool {
+        self.pressed != 0
+    }
+
+    pub fn read_p1(&self) -> u8 {
+        let mut lines: u8 = 0;
+        if self.select & 0x10 == 0 { lines |= self.pressed & 0x0F }
+        if self.select & 0x20 == 0 { lines |= self.pressed >> 4 }
+
+        // Input lines are active low
+        0xC0 | self.select | (!lines & 0x0F)
+    }
+
+    pub fn write_p1(&mut self, value: u8) {
+        self.select = value & 0x30;
+    }
+}
+
+impl Default for Joypad {
+    fn default() -> Self {
+        Joypad::new()
+    }
+}
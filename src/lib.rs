@@ -0,0 +1,27 @@
+pub mod cpu;
+pub mod gpu;
+pub mod registers;
+pub mod memory;
+pub mod mooneye;
+pub mod instructions;
+pub mod opcode_parser;
+pub mod alu;
+pub mod blargg;
+pub mod bus;
+pub mod cartridge;
+pub mod decode_table;
+pub mod disassembler;
+pub mod error;
+pub mod flags;
+pub mod header;
+pub mod interrupts;
+pub mod joypad;
+pub mod model;
+pub mod palettes;
+pub mod rom;
+pub mod rtc;
+pub mod serial;
+pub mod speed;
+pub mod timer;
+pub mod trace;
+pub mod frontend;
@@ -1,62 +1,138 @@
-#![feature(bigint_helper_methods)]
-extern crate core;
-
-mod cpu;
-mod gpu;
-mod registers;
-mod memory;
-mod instructions;
-mod opcode_parser;
-mod cycles;
-mod flags;
-mod rom;
-mod frontend;
-
-use std::{env};
+use std::{env, fs};
 use std::path::{Path};
-use cpu::Cpu;
-use rom::Rom;
-use crate::frontend::Frontend;
-use crate::gpu::Gpu;
-use crate::instructions::Opcode;
-use crate::memory::Memory;
+use rgbc::cartridge;
+use rgbc::cartridge::{Mapper, RomOnly};
+use rgbc::cpu::{Cpu, CpuMode};
+use rgbc::error::EmuError;
+use rgbc::rom::Rom;
+use rgbc::rtc::{Clock, CycleClock, Rtc, WallClock};
+use rgbc::frontend::Frontend;
+use rgbc::gpu::Gpu;
+use rgbc::memory::Memory;
+use rgbc::model::Model;
+use rgbc::mooneye;
+use rgbc::trace::TraceWriter;
 
 struct Emulator {
     frontend: Frontend,
     cpu: Cpu,
-    gpu: Gpu
+    gpu: Gpu,
 }
 
 impl Emulator {
-    fn new(bootrom: Rom) -> Emulator {
-        let mem = Memory::new(bootrom);
-        let cpu = Cpu::new(mem);
-        Emulator {
+    // Without a boot rom the cartridge starts in the post boot state of the model
+    fn new(bootrom: Option<Rom>, cartridge: Box<dyn Mapper>, model: Model, strict: bool, trace: Option<TraceWriter>) -> Result<Emulator, EmuError> {
+        let mut cpu = Cpu::new(Memory::with_cartridge(cartridge));
+        cpu.mem.set_model(model);
+        match bootrom {
+            Some(bootrom) => cpu.mem.map_boot_rom(bootrom.data)?,
+            None => cpu.skip_boot_rom(model),
+        }
+        cpu.set_strict(strict);
+        cpu.mem.set_stub_ly(trace.is_some());
+        cpu.set_trace(trace);
+        Ok(Emulator {
             frontend : Frontend::new(),
             cpu,
-            gpu : Gpu::new()
-        }
+            gpu : Gpu::new(),
+        })
     }
 
-    fn run(&mut self) {
+    fn run(&mut self) -> Result<(), EmuError> {
         loop {
 
-            if !self.frontend.is_open() { return; }
+            if !self.frontend.is_open() { return Ok(()); }
 
-            self.cpu.step();
+            self.cpu.step()?;
             self.gpu.step(&self.cpu.mem);
-            self.frontend.step(&self.gpu);
+            self.frontend.step(&self.gpu, &mut self.cpu.mem);
+
+            if *self.cpu.mode() == CpuMode::Stopped {
+                self.frontend.idle(&mut self.cpu.mem);
+            }
         }
     }
 }
 
 fn main() {
-    let args: Vec<String> = env::args().collect();
+    let args: Vec<String> = env::args().skip(1).collect();
+    let (flags, paths): (Vec<&String>, Vec<&String>) = args.iter().partition(|arg| arg.starts_with("--"));
+    let strict = flags.iter().any(|flag| *flag == "--strict");
+
+    // Lists which mooneye roms under the directory pass, without opening a window
+    if let Some(dir) = flags.iter().find_map(|flag| flag.strip_prefix("--mooneye=")) {
+        mooneye::list(Path::new(dir)).expect("Failed to read mooneye roms");
+        return;
+    }
+
+    let trace = flags.iter()
+        .find_map(|flag| flag.strip_prefix("--trace="))
+        .map(|path| TraceWriter::create(Path::new(path)).expect("Failed to create trace file"));
+
+    let mut paths = paths.into_iter();
+    let boot_rom = if flags.iter().any(|flag| *flag == "--no-bootrom") {
+        None
+    } else {
+        let boot_rom_arg = paths.next().expect("First argument must contain boot rom path");
+        Some(Rom::new(Path::new(boot_rom_arg)).expect("Failed to read boot rom"))
+    };
+    // Cartridge clocks follow real time unless emulated time is asked for, and jump ahead by the offset
+    // unless a save brings its own clock
+    let rtc_offset = flags.iter()
+        .find_map(|flag| flag.strip_prefix("--rtc-offset="))
+        .map_or(0, |seconds| seconds.parse().expect("Rtc offset must be a number of seconds"));
+    let clock: Box<dyn Clock> = if flags.iter().any(|flag| *flag == "--rtc=cycles") {
+        Box::new(CycleClock::new())
+    } else {
+        Box::new(WallClock)
+    };
+    let rtc = Rtc::new(clock, rtc_offset);
+
+    // Without a cartridge the boot rom runs on its own and stops at the logo check
+    let cartridge_path = paths.next();
+    let (header, mut cartridge): (_, Box<dyn Mapper>) = match cartridge_path {
+        Some(path) => match cartridge::load(Rom::new(Path::new(path)).expect("Failed to read cartridge"), rtc) {
+            Ok((header, cartridge)) => (Some(header), cartridge),
+            Err(e) => {
+                eprintln!("{e}");
+                return;
+            }
+        },
+        None => (None, Box::new(RomOnly::new(Vec::new(), 0))),
+    };
+    if header.as_ref().is_some_and(|header| !header.is_header_checksum_valid()) {
+        eprintln!("Header checksum mismatch, a real boot rom would lock up");
+    }
+
+    // Battery backed ram is kept next to the cartridge, game.sav for game.gb
+    let save_path = cartridge_path
+        .filter(|_| header.as_ref().is_some_and(|header| header.has_battery()))
+        .map(|path| Path::new(path).with_extension("sav"));
+    if let Some(data) = save_path.as_ref().and_then(|path| fs::read(path).ok()) {
+        cartridge.load_save_data(&data);
+    }
+
+    // The header picks the model unless one is given
+    let model = flags.iter()
+        .find_map(|flag| flag.strip_prefix("--model="))
+        .map(|name| Model::parse(name).expect("Model must be one of dmg, mgb, cgb or agb"))
+        .or(header.as_ref().map(|header| header.model()))
+        .unwrap_or(Model::Dmg);
 
-    let boot_rom_arg: &String = &args.get(1).expect("First argument must contain boot rom path");
-    let boot_rom = Rom::new(Path::new(boot_rom_arg)).expect("Failed to read boot rom");
+    let mut emulator = match Emulator::new(boot_rom, cartridge, model, strict, trace) {
+        Ok(emulator) => emulator,
+        Err(e) => {
+            eprintln!("{e}");
+            return;
+        }
+    };
 
-    let mut emulator = Emulator::new(boot_rom);
-    emulator.run();
-    println!("{:?}", emulator.cpu);
+    if let Err(e) = emulator.run() {
+        eprintln!("{e}");
+    }
+    if let Some(path) = save_path {
+        if let Err(e) = fs::write(&path, emulator.cpu.mem.cartridge.save_data()) {
+            eprintln!("Failed to write {}: {e}", path.display());
+        }
+    }
 }
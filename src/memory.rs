@@ -1,23 +1,82 @@
 use std::fmt::{Debug, Formatter};
-use crate::Rom;
+use crate::bus::Bus;
+use crate::error::EmuError;
+use crate::cartridge::{Mapper, RomOnly};
+use crate::interrupts::{Interrupt, Interrupts, IE_ADDR, IF_ADDR};
+use crate::joypad::{Button, Joypad, P1_ADDR};
+use crate::model::Model;
+use crate::palettes::{compatibility_palettes, PaletteRam, BCPD_ADDR, BCPS_ADDR, OCPD_ADDR, OCPS_ADDR};
+use crate::rom::Rom;
+use crate::serial::{Serial, SB_ADDR, SC_ADDR};
+use crate::speed::{SpeedSwitch, KEY1_ADDR};
+use crate::timer::{Timer, DIV_ADDR, TAC_ADDR, TIMA_ADDR, TMA_ADDR};
 
+// Writing a non zero value unmaps the boot rom
+pub const BOOT_ADDR: u16 = 0xFF50;
+pub const LY_ADDR: u16 = 0xFF44;
+
+// Dmg and cgb boot rom sizes
+const BOOT_ROM_SIZES: [usize; 2] = [0x100, 0x900];
+
+// Io registers after the boot rom, from the Pan Docs power up sequence
+const POST_BOOT_IO: [(u16, u8); 31] = [
+    (TAC_ADDR, 0xF8), (IF_ADDR, 0xE1),
+    // Sound
+    (0xFF10, 0x80), (0xFF11, 0xBF), (0xFF12, 0xF3), (0xFF13, 0xFF), (0xFF14, 0xBF),
+    (0xFF16, 0x3F), (0xFF17, 0x00), (0xFF18, 0xFF), (0xFF19, 0xBF),
+    (0xFF1A, 0x7F), (0xFF1B, 0xFF), (0xFF1C, 0x9F), (0xFF1D, 0xFF), (0xFF1E, 0xBF),
+    (0xFF20, 0xFF), (0xFF21, 0x00), (0xFF22, 0x00), (0xFF23, 0xBF),
+    (0xFF24, 0x77), (0xFF25, 0xF3), (0xFF26, 0xF1),
+    // Lcd
+    (0xFF40, 0x91), (0xFF41, 0x85), (0xFF42, 0x00), (0xFF43, 0x00), (0xFF45, 0x00),
+    (0xFF47, 0xFC), (0xFF4A, 0x00), (0xFF4B, 0x00),
+];
+
+// Registers the dmg and cgb boot roms leave differently, the cgb has the fast serial clock bit
+const DMG_POST_BOOT_IO: [(u16, u8); 2] = [(SC_ADDR, 0x7E), (0xFF46, 0xFF)];
+const CGB_POST_BOOT_IO: [(u16, u8); 2] = [(SC_ADDR, 0x7F), (0xFF46, 0x00)];
+
+// The ® drawn after the cartridge logo
+const REGISTERED_TILE: [u8; 8] = [0x3C, 0x42, 0xB9, 0xA5, 0xB9, 0xA5, 0x42, 0x3C];
+
+// Address space of the cpu, routing every region to the component that owns it
 pub struct Memory {
-    pub data: [u8;0xffff]
+    pub cartridge: Box<dyn Mapper>,
+    // Overlaid on the cartridge rom until unmapped through BOOT_ADDR
+    boot_rom: Option<Vec<u8>>,
+    vram: [u8; 0x2000],
+    wram: [u8; 0x2000],
+    oam: [u8; 0xA0],
+    // Io registers without a component of their own
+    io: [u8; 0x80],
+    hram: [u8; 0x7F],
+    pub interrupts: Interrupts,
+    pub joypad: Joypad,
+    pub timer: Timer,
+    pub serial: Serial,
+    pub speed: SpeedSwitch,
+    pub bg_palettes: PaletteRam,
+    pub obj_palettes: PaletteRam,
+    // Cgb only registers like KEY1 are open bus on the other models
+    model: Model,
+    // Lcd dots into the current scanline
+    scanline_dots: u16,
+    // LY reads 0x90 to the cpu, Gameboy Doctor logs are recorded that way
+    stub_ly: bool,
 }
 
 impl Debug for Memory {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         let mut res = String::new();
-        // let rows = self.data.len() / 16;
         let rows = 16;
         for row in 0 .. rows {
             res.push_str(format!("{:#06x}: ", row * 16).as_str());
 
             for byte in 0 .. 16 {
-                res.push_str(format!("{:02x} ", self.data[row * 16 + byte]).as_str())
+                res.push_str(format!("{:02x} ", self.read_addr8((row * 16 + byte) as u16)).as_str())
             }
 
-            res.push_str("\n");
+            res.push('\n');
         }
 
         write!(f, "{}", res)
@@ -26,23 +85,276 @@ impl Debug for Memory {
 
 impl Memory {
 
-    pub fn new(bootrom: Rom) -> Memory {
-        let mut data: [u8;0xffff] = [0;0xffff];
-        data[0..bootrom.data.len()].copy_from_slice(bootrom.data.as_slice());
-        Memory { data }
+    // Rom without a mapper, mapped from 0x0000
+    pub fn new(rom: Rom) -> Memory {
+        Memory::with_cartridge(Box::new(RomOnly::new(rom.data, 0x2000)))
+    }
+
+    pub fn with_cartridge(cartridge: Box<dyn Mapper>) -> Memory {
+        Memory {
+            cartridge,
+            boot_rom: None,
+            vram: [0; 0x2000],
+            wram: [0; 0x2000],
+            oam: [0; 0xA0],
+            io: [0; 0x80],
+            hram: [0; 0x7F],
+            interrupts: Interrupts::new(),
+            joypad: Joypad::new(),
+            timer: Timer::new(),
+            serial: Serial::new(),
+            speed: SpeedSwitch::new(),
+            bg_palettes: PaletteRam::new(),
+            obj_palettes: PaletteRam::new(),
+            model: Model::Dmg,
+            scanline_dots: 0,
+            stub_ly: false,
+        }
+    }
+
+    // Dmg boot roms cover 0x0000-0x00FF, cgb ones also 0x0200-0x08FF around the cartridge header
+    pub fn map_boot_rom(&mut self, boot_rom: Vec<u8>) -> Result<(), EmuError> {
+        if !BOOT_ROM_SIZES.contains(&boot_rom.len()) {
+            return Err(EmuError::InvalidBootRom { size: boot_rom.len() });
+        }
+        self.boot_rom = Some(boot_rom);
+        Ok(())
+    }
+
+    pub fn set_model(&mut self, model: Model) {
+        self.model = model;
+    }
+
+    pub fn model(&self) -> Model {
+        self.model
+    }
+
+    pub fn set_stub_ly(&mut self, stub_ly: bool) {
+        self.stub_ly = stub_ly;
+    }
+
+    // Scanline the lcd is on, regardless of what the cpu reads
+    pub fn ly(&self) -> u8 {
+        self.io[(LY_ADDR - 0xFF00) as usize]
+    }
+
+    pub fn is_boot_rom_mapped(&self) -> bool {
+        self.boot_rom.is_some()
+    }
+
+    // Leaves the io registers, vram and palettes the way the boot rom of the model does
+    pub fn skip_boot_rom(&mut self, model: Model) {
+        self.model = model;
+        let model_io = if model.is_cgb() { CGB_POST_BOOT_IO } else { DMG_POST_BOOT_IO };
+        for (addr, value) in POST_BOOT_IO.into_iter().chain(model_io) {
+            self.write_addr8(addr, value);
+        }
+        self.timer.load_counter(if model.is_cgb() { 0x1EA0 } else { 0xABCC });
+        self.boot_rom = None;
+
+        self.draw_logo();
+        if model.is_cgb() {
+            self.load_compatibility_palettes();
+        }
+    }
+
+    // Every boot rom scales the cartridge logo up into tiles 1-24 followed by the ® tile,
+    // and centers them in the background map
+    fn draw_logo(&mut self) {
+        for i in 0..48 {
+            let byte = self.read_addr8(0x0104 + i);
+            for (half, nibble) in [byte >> 4, byte & 0x0F].into_iter().enumerate() {
+                // Every pixel and every line is doubled
+                let line = (0..4)
+                    .filter(|bit| nibble & (0x08 >> bit) != 0)
+                    .fold(0u8, |line, bit| line | 0xC0 >> (bit * 2));
+                let addr = 0x8010 + i * 8 + half as u16 * 4;
+                self.write_addr8(addr, line);
+                self.write_addr8(addr + 2, line);
+            }
+        }
+        for (i, line) in REGISTERED_TILE.into_iter().enumerate() {
+            self.write_addr8(0x8190 + i as u16 * 2, line);
+        }
+
+        self.write_addr8(0x9910, 0x19);
+        for tile in 1..=12 {
+            self.write_addr8(0x9903 + tile as u16, tile);
+            self.write_addr8(0x9923 + tile as u16, tile + 12);
+        }
+    }
+
+    // Cgb cartridges start with white background palettes, dmg cartridges get colors the cgb
+    // boot roms pick from a table of known titles
+    fn load_compatibility_palettes(&mut self) {
+        if self.read_addr8(0x0143) & 0x80 != 0 {
+            for palette in 0..8 {
+                for color in 0..4 {
+                    self.bg_palettes.set_color(palette, color, 0x7FFF);
+                }
+            }
+            return;
+        }
+
+        let [bg, obj0, obj1] = compatibility_palettes(|addr| self.read_addr8(addr));
+        for color in 0..4 {
+            self.bg_palettes.set_color(0, color, bg[color]);
+            self.obj_palettes.set_color(0, color, obj0[color]);
+            self.obj_palettes.set_color(1, color, obj1[color]);
+        }
+    }
+
+    pub fn read_addr8(&self, addr: u16) -> u8 {
+        match addr {
+            0x0000..=0x7FFF => self.read_rom(addr),
+            0x8000..=0x9FFF => self.vram[(addr - 0x8000) as usize],
+            0xA000..=0xBFFF => self.cartridge.read_ram(addr),
+            0xC000..=0xDFFF => self.wram[(addr - 0xC000) as usize],
+            // Echo of work ram
+            0xE000..=0xFDFF => self.wram[(addr - 0xE000) as usize],
+            0xFE00..=0xFE9F => self.oam[(addr - 0xFE00) as usize],
+            // Unusable, reads as zero on dmg
+            0xFEA0..=0xFEFF => 0x00,
+            0xFF00..=0xFF7F => self.read_io(addr),
+            0xFF80..=0xFFFE => self.hram[(addr - 0xFF80) as usize],
+            IE_ADDR => self.interrupts.read_ie(),
+        }
+    }
+
+    fn read_rom(&self, addr: u16) -> u8 {
+        match &self.boot_rom {
+            Some(boot_rom) if (addr as usize) < boot_rom.len() && !(0x0100..=0x01FF).contains(&addr) => {
+                boot_rom[addr as usize]
+            }
+            _ => self.cartridge.read_rom(addr),
+        }
+    }
+
+    fn read_io(&self, addr: u16) -> u8 {
+        match addr {
+            P1_ADDR => self.joypad.read_p1(),
+            SB_ADDR => self.serial.read_sb(),
+            SC_ADDR => self.serial.read_sc(),
+            DIV_ADDR => self.timer.read_div(),
+            TIMA_ADDR => self.timer.read_tima(),
+            TMA_ADDR => self.timer.read_tma(),
+            TAC_ADDR => self.timer.read_tac(),
+            IF_ADDR => self.interrupts.read_if(),
+            LY_ADDR if self.stub_ly => 0x90,
+            KEY1_ADDR if self.model.is_cgb() => self.speed.read_key1(),
+            KEY1_ADDR => 0xFF,
+            BOOT_ADDR => 0xFF,
+            BCPS_ADDR => self.bg_palettes.read_index(),
+            BCPD_ADDR => self.bg_palettes.read_data(),
+            OCPS_ADDR => self.obj_palettes.read_index(),
+            OCPD_ADDR => self.obj_palettes.read_data(),
+            _ => self.io[(addr - 0xFF00) as usize]
+        }
     }
 
-    pub fn read_addr8(&self, addr: u16) -> u8 { self.data[addr as usize] }
     pub fn read_addr16(&self, addr: u16) -> u16 {
-        self.data[addr as usize] as u16 | (self.data[(addr + 1) as usize] as u16) << 8
+        self.read_addr8(addr) as u16 | (self.read_addr8(addr.wrapping_add(1)) as u16) << 8
     }
 
     pub fn write_addr8(&mut self, addr: u16, value: u8) {
-        self.data[addr as usize] = value
+        match addr {
+            0x0000..=0x7FFF => self.cartridge.write_rom(addr, value),
+            0x8000..=0x9FFF => self.vram[(addr - 0x8000) as usize] = value,
+            0xA000..=0xBFFF => self.cartridge.write_ram(addr, value),
+            0xC000..=0xDFFF => self.wram[(addr - 0xC000) as usize] = value,
+            0xE000..=0xFDFF => self.wram[(addr - 0xE000) as usize] = value,
+            0xFE00..=0xFE9F => self.oam[(addr - 0xFE00) as usize] = value,
+            0xFEA0..=0xFEFF => {}
+            0xFF00..=0xFF7F => self.write_io(addr, value),
+            0xFF80..=0xFFFE => self.hram[(addr - 0xFF80) as usize] = value,
+            IE_ADDR => self.interrupts.write_ie(value),
+        }
     }
+
+    fn write_io(&mut self, addr: u16, value: u8) {
+        match addr {
+            P1_ADDR => self.joypad.write_p1(value),
+            SB_ADDR => self.serial.write_sb(value),
+            SC_ADDR => self.serial.write_sc(value),
+            DIV_ADDR => self.timer.reset_div(&mut self.interrupts),
+            TIMA_ADDR => self.timer.write_tima(value),
+            TMA_ADDR => self.timer.write_tma(value),
+            TAC_ADDR => self.timer.write_tac(value, &mut self.interrupts),
+            IF_ADDR => self.interrupts.write_if(value),
+            KEY1_ADDR if self.model.is_cgb() => self.speed.write_key1(value),
+            KEY1_ADDR => {}
+            BOOT_ADDR => if value != 0 { self.boot_rom = None },
+            BCPS_ADDR => self.bg_palettes.write_index(value),
+            BCPD_ADDR => self.bg_palettes.write_data(value),
+            OCPS_ADDR => self.obj_palettes.write_index(value),
+            OCPD_ADDR => self.obj_palettes.write_data(value),
+            _ => self.io[(addr - 0xFF00) as usize] = value
+        }
+    }
+
     pub fn write_addr16(&mut self, addr: u16, value: u16){
-        self.data[addr as usize] = value as u8;
-        self.data[(addr + 1) as usize] = (value >> 8) as u8;
+        self.write_addr8(addr, value as u8);
+        self.write_addr8(addr.wrapping_add(1), (value >> 8) as u8);
     }
 
-}
\ No newline at end of file
+    // Rom bank mapped at addr
+    pub fn rom_bank(&self, addr: u16) -> u16 {
+        self.cartridge.rom_bank(addr)
+    }
+
+    // Advances components clocked by the cpu, in cpu cycles
+    pub fn tick(&mut self, cycles: u8) {
+        self.timer.tick(cycles, &mut self.interrupts);
+        self.serial.tick(cycles, &mut self.interrupts);
+
+        // The lcd always runs at single speed
+        let dots = if self.speed.is_double_speed() { cycles / 2 } else { cycles };
+        self.cartridge.tick(dots);
+        self.scanline_dots += dots as u16;
+
+        if self.scanline_dots >= 456 {
+            self.scanline_dots -= 456;
+
+            let scanline: u8 = self.ly();
+            let value = if scanline >= 153 { 0 } else { scanline + 1 };
+
+            self.write_addr8(LY_ADDR, value);
+
+            if value == 144 {
+                self.interrupts.request(Interrupt::VBlank);
+            }
+        }
+    }
+
+    pub fn set_button(&mut self, button: Button, pressed: bool) {
+        if self.joypad.set_pressed(button, pressed) {
+            self.interrupts.request(Interrupt::Joypad);
+        }
+    }
+
+}
+
+impl Bus for Memory {
+    fn read8(&mut self, addr: u16) -> u8 { self.read_addr8(addr) }
+    fn write8(&mut self, addr: u16, value: u8) { self.write_addr8(addr, value) }
+    // Reads have no side effects yet
+    fn peek(&self, addr: u16) -> u8 { self.read_addr8(addr) }
+
+    fn tick(&mut self, cycles: u8) { Memory::tick(self, cycles) }
+
+    fn skip_boot_rom(&mut self, model: Model) { Memory::skip_boot_rom(self, model) }
+
+    fn pending_interrupt(&self) -> Option<Interrupt> { self.interrupts.pending() }
+    fn acknowledge_interrupt(&mut self, interrupt: Interrupt) { self.interrupts.acknowledge(interrupt) }
+
+    fn stop(&mut self) -> bool {
+        self.timer.reset_div(&mut self.interrupts);
+        if !self.model.is_cgb() || !self.speed.is_armed() { return false; }
+        self.speed.switch();
+        true
+    }
+
+    fn any_button_pressed(&self) -> bool { self.joypad.any_pressed() }
+
+    fn rom_bank(&self, addr: u16) -> u16 { Memory::rom_bank(self, addr) }
+}
@@ -0,0 +1,26 @@
+// Hardware revision, decides the state left behind when the boot rom is skipped
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum Model {
+    Dmg,
+    // Game Boy Pocket
+    Mgb,
+    Cgb,
+    // Game Boy Advance running Game Boy software
+    Agb,
+}
+
+impl Model {
+    pub fn parse(name: &str) -> Option<Model> {
+        match name.to_ascii_lowercase().as_str() {
+            "dmg" => Some(Model::Dmg),
+            "mgb" => Some(Model::Mgb),
+            "cgb" => Some(Model::Cgb),
+            "agb" => Some(Model::Agb),
+            _ => None,
+        }
+    }
+
+    pub fn is_cgb(&self) -> bool {
+        matches!(self, Model::Cgb | Model::Agb)
+    }
+}
@@ -0,0 +1,93 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use crate::cartridge;
+use crate::cpu::{Cpu, CpuMode};
+use crate::error::EmuError;
+use crate::memory::Memory;
+use crate::rom::Rom;
+use crate::rtc::{CycleClock, Rtc};
+
+// Runs mooneye test roms without a frontend, they end with LD B,B and leave a signature in the registers
+
+// About 12 seconds of emulated time, every acceptance test finishes well within a second
+pub const CYCLE_BUDGET: u64 = 50_000_000;
+
+// Fibonacci numbers in B, C, D, E, H and L
+const PASS_SIGNATURE: [u8; 6] = [3, 5, 8, 13, 21, 34];
+const FAIL_SIGNATURE: [u8; 6] = [0x42; 6];
+
+#[derive(PartialEq, Debug)]
+pub enum MooneyeStatus {
+    Passed,
+    Failed,
+    // Breakpoint hit with neither signature in the registers
+    UnknownSignature([u8; 6]),
+    // The cycle budget ran out, or the cpu stopped, before the breakpoint
+    TimedOut,
+}
+
+#[derive(Debug)]
+pub struct MooneyeResult {
+    pub status: MooneyeStatus,
+    pub cycles: u64,
+}
+
+pub fn run(rom: Rom, cycle_budget: u64) -> Result<MooneyeResult, EmuError> {
+    let (header, cartridge) = cartridge::load(rom, Rtc::new(Box::new(CycleClock::new()), 0))?;
+    let mut cpu = Cpu::new(Memory::with_cartridge(cartridge));
+    // The misc tests for the cgb set the cgb flag in their header
+    cpu.skip_boot_rom(header.model());
+
+    while cpu.cycles() < cycle_budget {
+        cpu.step()?;
+        // Nothing presses a button without a frontend, a stopped cpu never wakes up
+        if *cpu.mode() == CpuMode::Stopped { break; }
+        if !cpu.take_breakpoint() { continue; }
+
+        let regs = cpu.registers();
+        let signature = [regs.b, regs.c, regs.d, regs.e, regs.h, regs.l];
+        let status = match signature {
+            PASS_SIGNATURE => MooneyeStatus::Passed,
+            FAIL_SIGNATURE => MooneyeStatus::Failed,
+            _ => MooneyeStatus::UnknownSignature(signature),
+        };
+        return Ok(MooneyeResult { status, cycles: cpu.cycles() });
+    }
+
+    Ok(MooneyeResult { status: MooneyeStatus::TimedOut, cycles: cpu.cycles() })
+}
+
+// Every .gb file under dir, sorted so listings can be compared between runs
+pub fn find_roms(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut roms = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            roms.extend(find_roms(&path)?);
+        } else if path.extension().is_some_and(|extension| extension == "gb") {
+            roms.push(path);
+        }
+    }
+    roms.sort();
+    Ok(roms)
+}
+
+// Runs every rom under dir and prints which ones pass, returns the number of passes and roms
+pub fn list(dir: &Path) -> io::Result<(usize, usize)> {
+    let roms = find_roms(dir)?;
+    let mut passed = 0;
+    for path in &roms {
+        let name = path.strip_prefix(dir).unwrap_or(path).display();
+        match run(Rom::new(path)?, CYCLE_BUDGET) {
+            Ok(MooneyeResult { status: MooneyeStatus::Passed, .. }) => {
+                passed += 1;
+                println!("pass  {name}");
+            }
+            Ok(result) => println!("FAIL  {name} ({:?})", result.status),
+            Err(e) => println!("FAIL  {name} ({e})"),
+        }
+    }
+    println!("{passed}/{} passed", roms.len());
+    Ok((passed, roms.len()))
+}
@@ -26,21 +26,61 @@ impl OpsTarget8 {
     }
 }
 
+impl OpsTarget8 {
+    // Immediate bytes following the opcode
+    fn operand_length(&self) -> u8 {
+        match self {
+            PcAddr8 => 1,
+            PcAddr16Addr8 => 2,
+            _ => 0,
+        }
+    }
+}
+
+impl OpsTarget16 {
+    fn operand_length(&self) -> u8 {
+        match self {
+            PcAddr16 | PcAddr16Addr16 => 2,
+            _ => 0,
+        }
+    }
+}
+
+impl Instruction {
+    // Length in bytes including the opcode
+    pub fn length(&self) -> u8 {
+        match self {
+            AddA { src, .. } | SubA { src, .. } | AndA { src } | XorA { src } | OrA { src } | CompareA { src } => {
+                1 + src.operand_length()
+            }
+            Inc { dst } | Dec { dst } => 1 + dst.operand_length(),
+            Inc16 { dst } | Dec16 { dst } => 1 + dst.operand_length(),
+            Load { dst, src } | LoadDstAddr { dst, src } | LoadSrcAddr { dst, src } => {
+                1 + dst.operand_length() + src.operand_length()
+            }
+            Load16 { dst, src } => 1 + dst.operand_length() + src.operand_length(),
+            Stop | AddSpAddr8ToSp | LoadSpAddSpAddr8ToHl | JumpReg | JumpRegIf { .. } | NestedInstruction => 2,
+            Jump | JumpIf { .. } | Call | CallIf { .. } => 3,
+            _ => 1,
+        }
+    }
+}
+
 pub trait InstructionParser {
-    fn to_instruction(&self) -> Instruction;
+    fn to_instruction(&self) -> Option<Instruction>;
     fn to_bit_instruction(&self) -> BitInstruction;
 }
 
 // Matching opcodes from https://izik1.github.io/gbops/
 impl InstructionParser for Opcode {
-    fn to_instruction(&self) -> Instruction {
+    fn to_instruction(&self) -> Option<Instruction> {
         let opcode = self;
         let op_mod8: u8 = opcode.value % 8;
 
-        match opcode.value {
+        let instruction = match opcode.value {
             // Column 1
             0x00 => Noop,
-            0x08 => Load16 { dst: PcAddr16, src: SP.to_opst16() },
+            0x08 => Load16 { dst: PcAddr16Addr16, src: SP.to_opst16() },
             0x10 => Stop,
             0x18 => JumpReg,
             0x20 => JumpRegIf { flag: Flag::NZ },
@@ -92,9 +132,8 @@ impl InstructionParser for Opcode {
             // Column 2
             0x76 => Halt,
             0x40..=0x7F => {
-                let src: OpsTarget8 = opcode.low_opst8();
-                let dst: OpsTarget8 = opcode.high_opst8();
-                if src.eq(&dst) { Noop } else { Load { dst, src } }
+                // Loads of a register into itself do nothing, but still decode as loads
+                Load { dst: opcode.high_opst8(), src: opcode.low_opst8() }
             }
 
             // Column 3
@@ -124,16 +163,16 @@ impl InstructionParser for Opcode {
             0xE1 => Pop { reg16: HL },
             0xE9 => JumpHL,
             0xF1 => Pop { reg16: AF },
-            0xF9 => Load16 { dst: PC, src: HL.to_opst16() },
+            0xF9 => Load16 { dst: SP.to_opst16(), src: HL.to_opst16() },
 
             0xC2 => JumpIf { flag: Flag::NZ },
             0xCA => JumpIf { flag: Flag::Z },
             0xD2 => JumpIf { flag: Flag::NC },
             0xDA => JumpIf { flag: Flag::C },
             0xE2 => LoadDstAddr { dst: C.to_opst8(), src: A.to_opst8() },
-            0xEA => Load { dst: PcAddr8, src: A.to_opst8() },
+            0xEA => Load { dst: PcAddr16Addr8, src: A.to_opst8() },
             0xF2 => LoadSrcAddr { dst: A.to_opst8(), src: C.to_opst8() },
-            0xFA => Load { dst: A.to_opst8(), src: PcAddr8 },
+            0xFA => Load { dst: A.to_opst8(), src: PcAddr16Addr8 },
 
             0xC3 => Jump,
             0xCB => NestedInstruction,
@@ -164,15 +203,19 @@ impl InstructionParser for Opcode {
                 let op_base: u8 = (opcode.value / 8) % 8;
                 Restart { addr: (op_base * 8) as u16 }
             }
-            _ => panic!("Opcode {opcode:?} not parsed")
-        }
+
+            op @ (0xD3 | 0xDB | 0xDD | 0xE3 | 0xE4 | 0xEB | 0xEC | 0xED | 0xF4 | 0xFC | 0xFD) => {
+                Illegal { opcode: op }
+            }
+            _ => return None
+        };
+        Some(instruction)
     }
 
     fn to_bit_instruction(&self) -> BitInstruction {
         let opcode = self;
         let target: OpsTarget8 = opcode.low_opst8();
         match opcode.value {
-            // TODO carry flag?
             0x00..=0x07 => RotateLeft { dst: target, carry: false },
             0x08..=0x0F => RotateRight { dst: target, carry: false },
             0x10..=0x17 => RotateLeft { dst: target, carry: true },
@@ -184,7 +227,6 @@ impl InstructionParser for Opcode {
             0x40..=0x7F => BitTest { src: target, bit: (opcode.value / 8) % 8 },
             0x80..=0xBF => BitReset { dst: target, bit: (opcode.value / 8) % 8 },
             0xC0..=0xFF => BitSet { dst: target, bit: (opcode.value / 8) % 8 },
-            _ => panic!("Bit opcode {opcode:?} not parsed")
         }
     }
 }
@@ -220,8 +262,9 @@ impl Opcode {
         self.to_opst8_impl(bytes)
     }
 
+    // Only the low 3 bits select the target
     fn to_opst8_impl(&self, bytes: u8) -> OpsTarget8 {
-        match bytes {
+        match bytes & 0x7 {
             0x0 => B.to_opst8(),
             0x1 => C.to_opst8(),
             0x2 => D.to_opst8(),
@@ -229,8 +272,7 @@ impl Opcode {
             0x4 => H.to_opst8(),
             0x5 => L.to_opst8(),
             0x6 => HL.to_opst8_addr(),
-            0x7 => A.to_opst8(),
-            _ => panic!("Input must be in range 0x0 ..= 0x7")
+            _ => A.to_opst8(),
         }
     }
 }
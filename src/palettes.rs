@@ -0,0 +1,151 @@
+pub const BCPS_ADDR: u16 = 0xFF68;
+pub const BCPD_ADDR: u16 = 0xFF69;
+pub const OCPS_ADDR: u16 = 0xFF6A;
+pub const OCPD_ADDR: u16 = 0xFF6B;
+
+// Cgb color ram, eight palettes of four little endian rgb555 colors, reached through an
+// index register and a data register
+#[derive(Debug)]
+pub struct PaletteRam {
+    data: [u8; 64],
+    index: u8,
+    // Advance the index after every data write
+    auto_increment: bool,
+}
+
+impl PaletteRam {
+    pub fn new() -> Self {
+        PaletteRam { data: [0; 64], index: 0, auto_increment: false }
+    }
+
+    pub fn color(&self, palette: usize, color: usize) -> u16 {
+        let i = palette * 8 + color * 2;
+        u16::from_le_bytes([self.data[i], self.data[i + 1]])
+    }
+
+    pub fn set_color(&mut self, palette: usize, color: usize, rgb555: u16) {
+        let i = palette * 8 + color * 2;
+        self.data[i..i + 2].copy_from_slice(&rgb555.to_le_bytes());
+    }
+
+    // Bit 6 is unused and reads as set
+    pub fn read_index(&self) -> u8 {
+        (self.auto_increment as u8) << 7 | 0x40 | self.index
+    }
+
+    pub fn write_index(&mut self, value: u8) {
+        self.index = value & 0x3F;
+        self.auto_increment = value & 0x80 != 0;
+    }
+
+    pub fn read_data(&self) -> u8 {
+        self.data[self.index as usize]
+    }
+
+    pub fn write_data(&mut self, value: u8) {
+        self.data[self.index as usize] = value;
+        if self.auto_increment {
+            self.index = (self.index + 1) & 0x3F;
+        }
+    }
+}
+
+impl Default for PaletteRam {
+    fn default() -> Self {
+        PaletteRam::new()
+    }
+}
+
+// Colors the cgb boot rom keeps for dmg cartridges, four per palette
+#[rustfmt::skip]
+const COMPATIBILITY_COLORS: [u16; 120] = [
+    0x7FFF, 0x32BF, 0x00D0, 0x0000, 0x639F, 0x4279, 0x15B0, 0x04CB,
+    0x7FFF, 0x6E31, 0x454A, 0x0000, 0x7FFF, 0x1BEF, 0x0200, 0x0000,
+    0x7FFF, 0x421F, 0x1CF2, 0x0000, 0x7FFF, 0x5294, 0x294A, 0x0000,
+    0x7FFF, 0x03FF, 0x012F, 0x0000, 0x7FFF, 0x03EF, 0x01D6, 0x0000,
+    0x7FFF, 0x42B5, 0x3DC8, 0x0000, 0x7E74, 0x03FF, 0x0180, 0x0000,
+    0x67FF, 0x77AC, 0x1A13, 0x2D6B, 0x7ED6, 0x4BFF, 0x2175, 0x0000,
+    0x53FF, 0x4A5F, 0x7E52, 0x0000, 0x4FFF, 0x7ED2, 0x3A4C, 0x1CE0,
+    0x03ED, 0x7FFF, 0x255F, 0x0000, 0x036A, 0x021F, 0x03FF, 0x7FFF,
+    0x7FFF, 0x01DF, 0x0112, 0x0000, 0x231F, 0x035F, 0x00F2, 0x0009,
+    0x7FFF, 0x03EA, 0x011F, 0x0000, 0x299F, 0x001A, 0x000C, 0x0000,
+    0x7FFF, 0x027F, 0x001F, 0x0000, 0x7FFF, 0x03E0, 0x0206, 0x0120,
+    0x7FFF, 0x7EEB, 0x001F, 0x7C00, 0x7FFF, 0x3FFF, 0x7E00, 0x001F,
+    0x7FFF, 0x03FF, 0x001F, 0x0000, 0x03FF, 0x001F, 0x000C, 0x0000,
+    0x7FFF, 0x033F, 0x0193, 0x0000, 0x0000, 0x4200, 0x037F, 0x7FFF,
+    0x7FFF, 0x7E8C, 0x7C00, 0x0000, 0x7FFF, 0x1BEF, 0x6180, 0x0000,
+];
+
+// First color of the first object, second object and background palettes. Most start at a
+// palette, a few start a color early and reuse the end of the one before.
+#[rustfmt::skip]
+const COMPATIBILITY_COMBINATIONS: [[usize; 3]; 51] = [
+    [4 * 4, 4 * 4, 29 * 4], [18 * 4, 18 * 4, 18 * 4], [20 * 4, 20 * 4, 20 * 4], [24 * 4, 24 * 4, 24 * 4],
+    [9 * 4, 9 * 4, 9 * 4], [0, 0, 0], [27 * 4, 27 * 4, 27 * 4], [5 * 4, 5 * 4, 5 * 4],
+    [12 * 4, 12 * 4, 12 * 4], [26 * 4, 26 * 4, 26 * 4], [16 * 4, 8 * 4, 8 * 4], [4 * 4, 28 * 4, 28 * 4],
+    [4 * 4, 2 * 4, 2 * 4], [3 * 4, 4 * 4, 4 * 4], [4 * 4, 29 * 4, 29 * 4], [28 * 4, 4 * 4, 28 * 4],
+    [2 * 4, 17 * 4, 2 * 4], [16 * 4, 16 * 4, 8 * 4], [4 * 4, 4 * 4, 7 * 4], [4 * 4, 4 * 4, 18 * 4],
+    [4 * 4, 4 * 4, 20 * 4], [19 * 4, 19 * 4, 9 * 4], [4 * 4 - 1, 4 * 4 - 1, 11 * 4], [17 * 4, 17 * 4, 2 * 4],
+    [4 * 4, 4 * 4, 2 * 4], [4 * 4, 4 * 4, 3 * 4], [28 * 4, 28 * 4, 0], [3 * 4, 3 * 4, 0],
+    [0, 0, 4], [18 * 4, 22 * 4, 18 * 4], [20 * 4, 22 * 4, 20 * 4], [24 * 4, 22 * 4, 24 * 4],
+    [16 * 4, 22 * 4, 8 * 4], [17 * 4, 4 * 4, 13 * 4], [28 * 4 - 1, 0, 14 * 4], [28 * 4 - 1, 4 * 4, 15 * 4],
+    [19 * 4, 22 * 4, 9 * 4], [16 * 4, 28 * 4, 10 * 4], [4 * 4, 23 * 4, 28 * 4], [17 * 4, 22 * 4, 2 * 4],
+    [4 * 4, 0, 2 * 4], [4 * 4, 28 * 4, 3 * 4], [28 * 4, 3 * 4, 0], [3 * 4, 28 * 4, 4 * 4],
+    [21 * 4, 28 * 4, 4 * 4], [3 * 4, 28 * 4, 0], [25 * 4, 3 * 4, 28 * 4], [0, 28 * 4, 8 * 4],
+    [4 * 4, 3 * 4, 28 * 4], [28 * 4, 3 * 4, 6 * 4], [4 * 4, 28 * 4, 29 * 4],
+];
+
+// Sums of the title bytes of known Nintendo games, past the first 65 the sums repeat and the
+// fourth letter of the title tells the games apart
+#[rustfmt::skip]
+const TITLE_CHECKSUMS: [u8; 79] = [
+    0x00, 0x88, 0x16, 0x36, 0xD1, 0xDB, 0xF2, 0x3C, 0x8C, 0x92, 0x3D, 0x5C, 0x58, 0xC9, 0x3E, 0x70,
+    0x1D, 0x59, 0x69, 0x19, 0x35, 0xA8, 0x14, 0xAA, 0x75, 0x95, 0x99, 0x34, 0x6F, 0x15, 0xFF, 0x97,
+    0x4B, 0x90, 0x17, 0x10, 0x39, 0xF7, 0xF6, 0xA2, 0x49, 0x4E, 0x43, 0x68, 0xE0, 0x8B, 0xF0, 0xCE,
+    0x0C, 0x29, 0xE8, 0xB7, 0x86, 0x9A, 0x52, 0x01, 0x9D, 0x71, 0x9C, 0xBD, 0x5D, 0x6D, 0x67, 0x3F,
+    0x6B, 0xB3, 0x46, 0x28, 0xA5, 0xC6, 0xD3, 0x27, 0x61, 0x18, 0x66, 0x6A, 0xBF, 0x0D, 0xF4,
+];
+const UNIQUE_CHECKSUMS: usize = 65;
+
+// Fourth title letters for the repeated sums, in rows as long as the repeated part of the table
+const FOURTH_LETTERS: &[u8; 29] = b"BEFAARBEKEK R-URAR INAILICE R";
+
+// Combination for every checksum, followed by one for every fourth letter
+#[rustfmt::skip]
+const COMBINATION_PER_GAME: [u8; 94] = [
+    0, 4, 5, 35, 34, 3, 31, 15, 10, 5, 19, 36, 7, 37, 30, 44,
+    21, 32, 31, 20, 5, 33, 13, 14, 5, 29, 5, 18, 9, 3, 2, 26,
+    25, 25, 41, 42, 26, 45, 42, 45, 36, 38, 26, 42, 30, 41, 34, 34,
+    5, 42, 6, 5, 33, 25, 42, 42, 40, 2, 16, 25, 42, 42, 5, 0,
+    39, 36, 22, 25, 6, 32, 12, 36, 11, 39, 18, 39, 24, 31, 50, 17,
+    46, 6, 27, 0, 47, 41, 41, 0, 0, 19, 34, 23, 18, 29,
+];
+
+// Background, first and second object palettes the cgb boot rom picks for a dmg cartridge from its
+// header. Only Nintendo's own games are looked up, everything else gets the first combination.
+pub fn compatibility_palettes(header: impl Fn(u16) -> u8) -> [[u16; 4]; 3] {
+    let nintendo = match header(0x014B) {
+        0x33 => header(0x0144) == b'0' && header(0x0145) == b'1',
+        licensee => licensee == 0x01,
+    };
+    let game = if nintendo { compatibility_game(&header) } else { 0 };
+
+    let [obj0, obj1, bg] = COMPATIBILITY_COMBINATIONS[COMBINATION_PER_GAME[game] as usize];
+    let palette = |first: usize| std::array::from_fn(|color| COMPATIBILITY_COLORS[first + color]);
+    [palette(bg), palette(obj0), palette(obj1)]
+}
+
+fn compatibility_game(header: impl Fn(u16) -> u8) -> usize {
+    let checksum = (0x0134..=0x0143).fold(0u8, |sum, addr| sum.wrapping_add(header(addr)));
+    let fourth_letter = header(0x0137);
+    let repeated = TITLE_CHECKSUMS.len() - UNIQUE_CHECKSUMS;
+
+    let Some(i) = TITLE_CHECKSUMS.iter().position(|sum| *sum == checksum) else { return 0 };
+    if i < UNIQUE_CHECKSUMS {
+        return i;
+    }
+    // Later rows of letters reuse the same sums, no matching letter means an unknown game
+    (i - UNIQUE_CHECKSUMS..FOURTH_LETTERS.len()).step_by(repeated)
+        .find(|letter| FOURTH_LETTERS[*letter] == fourth_letter)
+        .map_or(0, |letter| UNIQUE_CHECKSUMS + letter)
+}
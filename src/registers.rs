@@ -1,6 +1,7 @@
 use std::fmt::{Debug, Formatter};
+use crate::flags::Flags;
 
-#[derive(PartialEq, Debug)]
+#[derive(PartialEq, Clone, Copy, Debug)]
 pub enum Register8 {
     // General registers
     A,
@@ -13,7 +14,7 @@ pub enum Register8 {
     L,
 }
 
-#[derive(PartialEq, Debug)]
+#[derive(PartialEq, Clone, Copy, Debug)]
 pub enum Register16 {
     // Combined registers
     AF,
@@ -30,7 +31,7 @@ pub enum Register16 {
 #[derive(Default)]
 pub struct Registers {
     pub a: u8,
-    pub f: u8,
+    pub f: Flags,
     pub b: u8,
     pub c: u8,
     pub d: u8,
@@ -42,7 +43,7 @@ pub struct Registers {
 
 impl Debug for Registers {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(f, "a: {:#04x}, f: {:#04x}, b: {:#04x}, c: {:#04x}, d: {:#04x}, e: {:#04x}, h: {:#04x}, l: {:#04x}, sp: {:#06x}", self.a, self.f, self.b, self.c, self.d, self.e, self.h, self.l, self.sp)
+        write!(f, "a: {:#04x}, f: {:#04x}, b: {:#04x}, c: {:#04x}, d: {:#04x}, e: {:#04x}, h: {:#04x}, l: {:#04x}, sp: {:#06x}", self.a, self.f.bits(), self.b, self.c, self.d, self.e, self.h, self.l, self.sp)
     }
 }
 
@@ -52,12 +53,12 @@ impl Registers {
     }
 
     pub fn af(&self) -> u16 {
-        combine_u8(self.a, self.f)
+        combine_u8(self.a, self.f.bits())
     }
 
     pub fn af_w(&mut self, value: u16) {
         self.a = high_byte(value);
-        self.f = low_byte(value);
+        self.f = Flags::from_bits(low_byte(value));
     }
 
     pub fn bc(&self) -> u16 {
@@ -90,7 +91,7 @@ impl Registers {
     pub fn read_reg8(&self, reg8: &Register8) -> u8 {
         match reg8 {
             Register8::A => self.a,
-            Register8::F => self.f,
+            Register8::F => self.f.bits(),
             Register8::B => self.b,
             Register8::C => self.c,
             Register8::D => self.d,
@@ -123,7 +124,7 @@ impl Registers {
     pub fn write_reg8(&mut self, reg8: &Register8, value: u8) {
         match reg8 {
             Register8::A => self.a = value,
-            Register8::F => self.f = value,
+            Register8::F => self.f = Flags::from_bits(value),
             Register8::B => self.b = value,
             Register8::C => self.c = value,
             Register8::D => self.d = value,
@@ -0,0 +1,166 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// Size of the footer save files keep the clock in, after the cartridge ram
+pub const SAVE_STATE_SIZE: usize = 48;
+
+// Marks saved timestamps of clocks other than unix time, unix time never reaches the top bit
+const NOT_UNIX_TIME: u64 = 1 << 63;
+
+// Time source of the cartridge clock, in whole seconds
+pub trait Clock {
+    fn now(&self) -> u64;
+    // Lcd clock cycles, 4 MHz in both speeds
+    fn tick(&mut self, _cycles: u8) {}
+    // Only unix time keeps counting between sessions
+    fn is_unix_time(&self) -> bool { false }
+}
+
+// Unix time, so time passing while the emulator is closed counts as well
+pub struct WallClock;
+
+impl Clock for WallClock {
+    fn now(&self) -> u64 {
+        SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |time| time.as_secs())
+    }
+
+    fn is_unix_time(&self) -> bool { true }
+}
+
+// Emulated time since power on, deterministic for tests and replays
+#[derive(Default)]
+pub struct CycleClock {
+    cycles: u64,
+}
+
+impl CycleClock {
+    pub const CYCLES_PER_SECOND: u64 = 4_194_304;
+
+    pub fn new() -> Self {
+        CycleClock { cycles: 0 }
+    }
+}
+
+impl Clock for CycleClock {
+    fn now(&self) -> u64 {
+        self.cycles / CycleClock::CYCLES_PER_SECOND
+    }
+
+    fn tick(&mut self, cycles: u8) {
+        self.cycles += cycles as u64;
+    }
+}
+
+// MBC3 real time clock, counting seconds, minutes, hours and a nine bit day counter.
+// Games read a copy latched through the mapper.
+pub struct Rtc {
+    clock: Box<dyn Clock>,
+    // Clock reading the counters were last brought up to date with
+    last_update: u64,
+    seconds: u8,
+    minutes: u8,
+    hours: u8,
+    days: u16,
+    halted: bool,
+    // Set when the day counter overflows, until the game clears it
+    day_carry: bool,
+    latched: [u8; 5],
+}
+
+impl Rtc {
+    // The counters start offset seconds ahead, a save state loaded later replaces them
+    pub fn new(clock: Box<dyn Clock>, offset: u64) -> Self {
+        let last_update = clock.now();
+        let mut rtc = Rtc { clock, last_update, seconds: 0, minutes: 0, hours: 0, days: 0, halted: false, day_carry: false, latched: [0; 5] };
+        rtc.advance(offset);
+        rtc
+    }
+
+    pub fn tick(&mut self, cycles: u8) {
+        self.clock.tick(cycles);
+    }
+
+    // Copies the counters into the registers games read
+    pub fn latch(&mut self) {
+        self.update();
+        self.latched = self.registers();
+    }
+
+    // Registers 0x08-0x0C are seconds, minutes, hours, day low and day high
+    pub fn read(&self, register: u8) -> u8 {
+        self.latched[(register - 0x08) as usize]
+    }
+
+    pub fn write(&mut self, register: u8, value: u8) {
+        self.update();
+        match register {
+            0x08 => self.seconds = value & 0x3F,
+            0x09 => self.minutes = value & 0x3F,
+            0x0A => self.hours = value & 0x1F,
+            0x0B => self.days = self.days & 0x100 | value as u16,
+            _ => {
+                self.days = self.days & 0xFF | (value as u16 & 0x01) << 8;
+                self.halted = value & 0x40 != 0;
+                self.day_carry = value & 0x80 != 0;
+            }
+        }
+        // Written values read back without latching again
+        self.latched[(register - 0x08) as usize] = self.registers()[(register - 0x08) as usize];
+    }
+
+    // Counters, latched registers and the clock reading, in the layout other emulators use:
+    // ten little endian 32 bit registers followed by a 64 bit timestamp, flagged unless it is unix time
+    pub fn save_state(&self) -> [u8; SAVE_STATE_SIZE] {
+        let mut state = [0; SAVE_STATE_SIZE];
+        for (i, register) in self.registers().into_iter().chain(self.latched).enumerate() {
+            state[i * 4] = register;
+        }
+        let timestamp = if self.clock.is_unix_time() { self.last_update } else { self.last_update | NOT_UNIX_TIME };
+        state[40..].copy_from_slice(&timestamp.to_le_bytes());
+        state
+    }
+
+    pub fn load_state(&mut self, state: &[u8; SAVE_STATE_SIZE]) {
+        let register = |i: usize| state[i * 4];
+        self.seconds = register(0) & 0x3F;
+        self.minutes = register(1) & 0x3F;
+        self.hours = register(2) & 0x1F;
+        self.days = (register(4) as u16 & 0x01) << 8 | register(3) as u16;
+        self.halted = register(4) & 0x40 != 0;
+        self.day_carry = register(4) & 0x80 != 0;
+        self.latched = [5, 6, 7, 8, 9].map(register);
+
+        // Time only passes between sessions when both are unix time, and timestamps from the future
+        // count as no time passed
+        let timestamp = u64::from_le_bytes(state[40..].try_into().unwrap());
+        let now = self.clock.now();
+        let unix_time = timestamp & NOT_UNIX_TIME == 0 && self.clock.is_unix_time();
+        self.last_update = if unix_time { timestamp.min(now) } else { now };
+    }
+
+    fn registers(&self) -> [u8; 5] {
+        let day_high = (self.days >> 8) as u8 | (self.halted as u8) << 6 | (self.day_carry as u8) << 7;
+        [self.seconds, self.minutes, self.hours, self.days as u8, day_high]
+    }
+
+    fn update(&mut self) {
+        let now = self.clock.now();
+        let elapsed = now.saturating_sub(self.last_update);
+        self.last_update = now;
+        self.advance(elapsed);
+    }
+
+    fn advance(&mut self, elapsed: u64) {
+        // Time spent halted is lost
+        if self.halted { return; }
+
+        let seconds = self.seconds as u64 + elapsed;
+        let minutes = self.minutes as u64 + seconds / 60;
+        let hours = self.hours as u64 + minutes / 60;
+        let days = self.days as u64 + hours / 24;
+        self.seconds = (seconds % 60) as u8;
+        self.minutes = (minutes % 60) as u8;
+        self.hours = (hours % 24) as u8;
+        self.days = (days % 512) as u16;
+        self.day_carry |= days >= 512;
+    }
+}
@@ -0,0 +1,62 @@
+use crate::interrupts::{Interrupt, Interrupts};
+
+pub const SB_ADDR: u16 = 0xFF01;
+pub const SC_ADDR: u16 = 0xFF02;
+
+// Cpu cycles to shift out a byte with the internal 8192 Hz clock
+const TRANSFER_CYCLES: u16 = 8 * 512;
+
+// SB and SC without a link partner, every transfer shifts in 0xFF
+#[derive(Default, Debug)]
+pub struct Serial {
+    data: u8,
+    control: u8,
+    // Cpu cycles until the transfer in progress completes
+    remaining: u16,
+    // Bytes sent since capturing was enabled, test roms print their results this way
+    output: Option<Vec<u8>>,
+}
+
+impl Serial {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn set_capture(&mut self, capture: bool) {
+        self.output = if capture { Some(Vec::new()) } else { None };
+    }
+
+    pub fn output(&self) -> &[u8] {
+        self.output.as_deref().unwrap_or_default()
+    }
+
+    pub fn tick(&mut self, cycles: u8, interrupts: &mut Interrupts) {
+        if self.remaining == 0 { return; }
+
+        self.remaining = self.remaining.saturating_sub(cycles as u16);
+        if self.remaining == 0 {
+            self.data = 0xFF;
+            self.control &= 0x7F;
+            interrupts.request(Interrupt::Serial);
+        }
+    }
+
+    pub fn read_sb(&self) -> u8 { self.data }
+    pub fn write_sb(&mut self, value: u8) { self.data = value }
+
+    // Unused bits read as 1
+    pub fn read_sc(&self) -> u8 { self.control | 0x7E }
+
+    // Bit 7 starts a transfer, which only completes on the internal clock selected by bit 0
+    pub fn write_sc(&mut self, value: u8) {
+        self.control = value & 0x81;
+        if value & 0x80 == 0 { return; }
+
+        if let Some(output) = &mut self.output {
+            output.push(self.data);
+        }
+        if value & 0x01 != 0 {
+            self.remaining = TRANSFER_CYCLES;
+        }
+    }
+}
@@ -0,0 +1,37 @@
+pub const KEY1_ADDR: u16 = 0xFF4D;
+
+// CGB speed switch, armed through KEY1 and performed by STOP
+#[derive(Default, Debug)]
+pub struct SpeedSwitch {
+    double_speed: bool,
+    armed: bool,
+}
+
+impl SpeedSwitch {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn is_double_speed(&self) -> bool {
+        self.double_speed
+    }
+
+    pub fn is_armed(&self) -> bool {
+        self.armed
+    }
+
+    // Toggles between 4 MHz and 8 MHz and clears the prepare bit
+    pub fn switch(&mut self) {
+        self.double_speed = !self.double_speed;
+        self.armed = false;
+    }
+
+    // Bit 7 is the current speed, bit 0 the prepare bit
+    pub fn read_key1(&self) -> u8 {
+        (self.double_speed as u8) << 7 | 0x7E | self.armed as u8
+    }
+
+    pub fn write_key1(&mut self, value: u8) {
+        self.armed = value & 0x01 != 0;
+    }
+}
@@ -0,0 +1,81 @@
+use crate::interrupts::{Interrupt, Interrupts};
+
+pub const DIV_ADDR: u16 = 0xFF04;
+pub const TIMA_ADDR: u16 = 0xFF05;
+pub const TMA_ADDR: u16 = 0xFF06;
+pub const TAC_ADDR: u16 = 0xFF07;
+
+// DIV, TIMA, TMA and TAC, clocked by the cpu clock so they follow double speed
+#[derive(Default, Debug)]
+pub struct Timer {
+    // DIV is the upper byte of this counter
+    counter: u16,
+    tima: u8,
+    tma: u8,
+    tac: u8,
+}
+
+impl Timer {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn tick(&mut self, cycles: u8, interrupts: &mut Interrupts) {
+        for _ in 0..cycles {
+            let counter = self.counter.wrapping_add(1);
+            self.set_counter(counter, interrupts);
+        }
+    }
+
+    // STOP and writes to DIV reset the whole counter
+    pub fn reset_div(&mut self, interrupts: &mut Interrupts) {
+        self.set_counter(0, interrupts);
+    }
+
+    // Sets the counter without the falling edge checks, for the state left by the boot rom
+    pub fn load_counter(&mut self, counter: u16) {
+        self.counter = counter;
+    }
+
+    pub fn read_div(&self) -> u8 { (self.counter >> 8) as u8 }
+    pub fn read_tima(&self) -> u8 { self.tima }
+    pub fn read_tma(&self) -> u8 { self.tma }
+    pub fn read_tac(&self) -> u8 { self.tac | 0xF8 }
+
+    pub fn write_tima(&mut self, value: u8) { self.tima = value }
+    pub fn write_tma(&mut self, value: u8) { self.tma = value }
+
+    pub fn write_tac(&mut self, value: u8, interrupts: &mut Interrupts) {
+        let input = self.input();
+        self.tac = value & 0x07;
+        // Disabling the timer or switching frequency can cause a falling edge
+        if input && !self.input() { self.increment_tima(interrupts) }
+    }
+
+    fn set_counter(&mut self, counter: u16, interrupts: &mut Interrupts) {
+        let input = self.input();
+        self.counter = counter;
+        if input && !self.input() { self.increment_tima(interrupts) }
+    }
+
+    // TIMA increments on the falling edge of the counter bit selected by TAC
+    fn input(&self) -> bool {
+        let bit = match self.tac & 0x03 {
+            0x0 => 9,
+            0x1 => 3,
+            0x2 => 5,
+            _ => 7,
+        };
+        self.tac & 0x04 != 0 && self.counter & (1 << bit) != 0
+    }
+
+    fn increment_tima(&mut self, interrupts: &mut Interrupts) {
+        let (value, overflow) = self.tima.overflowing_add(1);
+        if overflow {
+            self.tima = self.tma;
+            interrupts.request(Interrupt::Timer);
+        } else {
+            self.tima = value;
+        }
+    }
+}
@@ -0,0 +1,32 @@
+use std::fs::File;
+use std::io;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use crate::bus::Bus;
+use crate::registers::Registers;
+
+// Writes the cpu state before every instruction in the Gameboy Doctor format,
+// see https://github.com/robert/gameboy-doctor
+pub struct TraceWriter {
+    out: Box<dyn Write>,
+}
+
+impl TraceWriter {
+    pub fn new(out: Box<dyn Write>) -> Self {
+        TraceWriter { out }
+    }
+
+    pub fn create(path: &Path) -> io::Result<TraceWriter> {
+        File::create(path).map(|file| TraceWriter::new(Box::new(BufWriter::new(file))))
+    }
+
+    pub fn write<B: Bus>(&mut self, regs: &Registers, pc: u16, bus: &B) -> io::Result<()> {
+        let pcmem = [0, 1, 2, 3].map(|i| bus.peek(pc.wrapping_add(i)));
+        writeln!(
+            self.out,
+            "A:{:02X} F:{:02X} B:{:02X} C:{:02X} D:{:02X} E:{:02X} H:{:02X} L:{:02X} SP:{:04X} PC:{:04X} PCMEM:{:02X},{:02X},{:02X},{:02X}",
+            regs.a, regs.f.bits(), regs.b, regs.c, regs.d, regs.e, regs.h, regs.l, regs.sp, pc,
+            pcmem[0], pcmem[1], pcmem[2], pcmem[3],
+        )
+    }
+}
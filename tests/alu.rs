@@ -0,0 +1,282 @@
+// Checks every alu operation over all operand and incoming flag combinations against
+// reference results computed with wider integers, and DAA against the table in the
+// Game Boy programming manual
+
+use rgbc::alu;
+use rgbc::flags::Flags;
+
+fn flags(z: bool, n: bool, h: bool, c: bool) -> Flags {
+    let mut flags = Flags::default();
+    flags.set_z(z);
+    flags.set_n(n);
+    flags.set_h(h);
+    flags.set_c(c);
+    flags
+}
+
+// All 16 combinations of incoming flags
+fn all_flags() -> impl Iterator<Item = Flags> {
+    (0..16u8).map(|bits| Flags::from_bits(bits << 4))
+}
+
+#[test]
+fn add_and_adc() {
+    for a in 0..=255u8 {
+        for b in 0..=255u8 {
+            for carry in [false, true] {
+                let c = carry as u16;
+                let sum = a as u16 + b as u16 + c;
+                let expected = flags(sum & 0xFF == 0, false, (a & 0x0F) as u16 + (b & 0x0F) as u16 + c > 0x0F, sum > 0xFF);
+
+                for mut f in all_flags() {
+                    let value = alu::add(a, b, carry, &mut f);
+                    assert_eq!((value, f), (sum as u8, expected), "{a:#04X} + {b:#04X} + {c}");
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn sub_sbc_and_cp() {
+    for a in 0..=255u8 {
+        for b in 0..=255u8 {
+            for carry in [false, true] {
+                let c = carry as i16;
+                let difference = a as i16 - b as i16 - c;
+                let half = (a & 0x0F) as i16 - (b & 0x0F) as i16 - c;
+                let expected = flags(difference as u8 == 0, true, half < 0, difference < 0);
+
+                for mut f in all_flags() {
+                    let value = alu::sub(a, b, carry, &mut f);
+                    assert_eq!((value, f), (difference as u8, expected), "{a:#04X} - {b:#04X} - {c}");
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn logic() {
+    for a in 0..=255u8 {
+        for b in 0..=255u8 {
+            for f in all_flags() {
+                let mut and = f;
+                assert_eq!((alu::and(a, b, &mut and), and), (a & b, flags(a & b == 0, false, true, false)));
+                let mut xor = f;
+                assert_eq!((alu::xor(a, b, &mut xor), xor), (a ^ b, flags(a ^ b == 0, false, false, false)));
+                let mut or = f;
+                assert_eq!((alu::or(a, b, &mut or), or), (a | b, flags(a | b == 0, false, false, false)));
+            }
+        }
+    }
+}
+
+#[test]
+fn inc_and_dec() {
+    for a in 0..=255u8 {
+        for f in all_flags() {
+            let inc = (a as u16 + 1) as u8;
+            let mut inc_flags = f;
+            let value = alu::inc(a, &mut inc_flags);
+            assert_eq!((value, inc_flags), (inc, flags(inc == 0, false, a & 0x0F == 0x0F, f.c())), "inc {a:#04X}");
+
+            let dec = (a as i16 - 1) as u8;
+            let mut dec_flags = f;
+            let value = alu::dec(a, &mut dec_flags);
+            assert_eq!((value, dec_flags), (dec, flags(dec == 0, true, a & 0x0F == 0x00, f.c())), "dec {a:#04X}");
+        }
+    }
+}
+
+#[test]
+fn add16() {
+    // Every value of hl against operands on both sides of the bit 11 and bit 15 carries
+    let operands = [0x0000, 0x0001, 0x000F, 0x00FF, 0x0700, 0x07FF, 0x0800, 0x0FFF, 0x1000, 0x7FFF, 0x8000, 0xF000, 0xFFFF];
+    for a in 0..=0xFFFFu16 {
+        for b in operands {
+            let sum = a as u32 + b as u32;
+            let half = (a & 0x0FFF) + (b & 0x0FFF) > 0x0FFF;
+
+            for mut f in all_flags() {
+                let expected = flags(f.z(), false, half, sum > 0xFFFF);
+                let value = alu::add16(a, b, &mut f);
+                assert_eq!((value, f), (sum as u16, expected), "{a:#06X} + {b:#06X}");
+            }
+        }
+    }
+}
+
+#[test]
+fn add_signed8() {
+    // Flags only depend on the low byte, the high byte checks the sign extension
+    for high in [0x00, 0x7F, 0x80, 0xFF] {
+        for low in 0..=255u8 {
+            let a = u16::from_le_bytes([low, high]);
+            for b in 0..=255u8 {
+                let sum = (a as i32 + b as i8 as i32) as u16;
+                let expected = flags(false, false, (low & 0x0F) + (b & 0x0F) > 0x0F, low as u16 + b as u16 > 0xFF);
+
+                for mut f in all_flags() {
+                    let value = alu::add_signed8(a, b as i8, &mut f);
+                    assert_eq!((value, f), (sum, expected), "{a:#06X} + {}", b as i8);
+                }
+            }
+        }
+    }
+}
+
+type Operation = fn(u8, &mut Flags) -> u8;
+
+#[test]
+fn rotates_and_shifts() {
+    for a in 0..=255u8 {
+        for f in all_flags() {
+            let carry_in = f.c() as u16;
+            let wide = a as u16;
+            let bit7 = a & 0x80 != 0;
+            let bit0 = a & 0x01 != 0;
+
+            // (operation, result, carry out)
+            let cases: [(Operation, u16, bool); 8] = [
+                (|a, f| alu::rotate_left(a, false, f), wide << 1 | wide >> 7, bit7),
+                (|a, f| alu::rotate_left(a, true, f), wide << 1 | carry_in, bit7),
+                (|a, f| alu::rotate_right(a, false, f), wide >> 1 | wide << 7, bit0),
+                (|a, f| alu::rotate_right(a, true, f), wide >> 1 | carry_in << 7, bit0),
+                (alu::shift_left_arithmetic, wide << 1, bit7),
+                (alu::shift_right_arithmetic, wide >> 1 | wide & 0x80, bit0),
+                (alu::swap_nibbles, wide << 4 | wide >> 4, false),
+                (alu::shift_right_logical, wide >> 1, bit0),
+            ];
+
+            for (i, (operation, result, c)) in cases.into_iter().enumerate() {
+                let result = result as u8;
+                let mut out = f;
+                let value = operation(a, &mut out);
+                assert_eq!((value, out), (result, flags(result == 0, false, false, c)), "case {i} of {a:#04X}");
+            }
+        }
+    }
+}
+
+#[test]
+fn accumulator_rotates_clear_zero() {
+    for a in 0..=255u8 {
+        for f in all_flags() {
+            for carry in [false, true] {
+                let mut cb = f;
+                let expected = alu::rotate_left(a, carry, &mut cb);
+                cb.set_z(false);
+                let mut out = f;
+                assert_eq!((alu::rotate_left_a(a, carry, &mut out), out), (expected, cb));
+
+                let mut cb = f;
+                let expected = alu::rotate_right(a, carry, &mut cb);
+                cb.set_z(false);
+                let mut out = f;
+                assert_eq!((alu::rotate_right_a(a, carry, &mut out), out), (expected, cb));
+            }
+        }
+    }
+}
+
+#[test]
+fn bit_test() {
+    for a in 0..=255u8 {
+        for bit in 0..8 {
+            for mut f in all_flags() {
+                let expected = flags(a & (1 << bit) == 0, false, true, f.c());
+                alu::bit_test(a, bit, &mut f);
+                assert_eq!(f, expected);
+            }
+        }
+    }
+}
+
+// (n, c, h, upper digit range, lower digit range, correction, c out) from the programming manual
+type DaaRow = (bool, bool, bool, (u8, u8), (u8, u8), u8, bool);
+
+#[rustfmt::skip]
+const DAA_TABLE: [DaaRow; 13] = [
+    (false, false, false, (0x0, 0x9), (0x0, 0x9), 0x00, false),
+    (false, false, false, (0x0, 0x8), (0xA, 0xF), 0x06, false),
+    (false, false, true,  (0x0, 0x9), (0x0, 0x3), 0x06, false),
+    (false, false, false, (0xA, 0xF), (0x0, 0x9), 0x60, true),
+    (false, false, false, (0x9, 0xF), (0xA, 0xF), 0x66, true),
+    (false, false, true,  (0xA, 0xF), (0x0, 0x3), 0x66, true),
+    (false, true,  false, (0x0, 0x2), (0x0, 0x9), 0x60, true),
+    (false, true,  false, (0x0, 0x2), (0xA, 0xF), 0x66, true),
+    (false, true,  true,  (0x0, 0x3), (0x0, 0x3), 0x66, true),
+    (true,  false, false, (0x0, 0x9), (0x0, 0x9), 0x00, false),
+    (true,  false, true,  (0x0, 0x8), (0x6, 0xF), 0xFA, false),
+    (true,  true,  false, (0x7, 0xF), (0x0, 0x9), 0xA0, true),
+    (true,  true,  true,  (0x6, 0xF), (0x6, 0xF), 0x9A, true),
+];
+
+#[test]
+fn decimal_adjust_table() {
+    for (n, c, h, upper, lower, correction, c_out) in DAA_TABLE {
+        for a in 0..=255u8 {
+            let (hi, lo) = (a >> 4, a & 0x0F);
+            if hi < upper.0 || hi > upper.1 || lo < lower.0 || lo > lower.1 { continue; }
+
+            for z in [false, true] {
+                let mut f = flags(z, n, h, c);
+                let value = alu::decimal_adjust(a, &mut f);
+                let expected = a.wrapping_add(correction);
+                assert_eq!((value, f), (expected, flags(expected == 0, n, false, c_out)), "daa {a:#04X} n={n} c={c} h={h}");
+            }
+        }
+    }
+}
+
+#[test]
+fn decimal_adjust_after_bcd_arithmetic() {
+    // Every pair of bcd numbers gives the bcd sum and difference, with carry as the hundreds digit
+    let bcd = |value: u8| ((value / 10) << 4) | (value % 10);
+    for x in 0..100u8 {
+        for y in 0..100u8 {
+            let mut f = Flags::default();
+            let sum = alu::add(bcd(x), bcd(y), false, &mut f);
+            let value = alu::decimal_adjust(sum, &mut f);
+            assert_eq!((value, f.c()), (bcd((x + y) % 100), x + y >= 100), "{x} + {y}");
+
+            let mut f = Flags::default();
+            let difference = alu::sub(bcd(x), bcd(y), false, &mut f);
+            let value = alu::decimal_adjust(difference, &mut f);
+            assert_eq!((value, f.c()), (bcd((100 + x - y) % 100), x < y), "{x} - {y}");
+        }
+    }
+}
+
+#[test]
+fn decimal_adjust_flags() {
+    for a in 0..=255u8 {
+        for mut f in all_flags() {
+            let n = f.n();
+            let c = f.c();
+            let value = alu::decimal_adjust(a, &mut f);
+            assert_eq!((f.z(), f.n(), f.h()), (value == 0, n, false));
+            // Carry is never cleared
+            assert!(f.c() || !c);
+        }
+    }
+}
+
+#[test]
+fn complement_and_carry_flag() {
+    for a in 0..=255u8 {
+        for mut f in all_flags() {
+            let expected = flags(f.z(), true, true, f.c());
+            assert_eq!((alu::complement(a, &mut f), f), (!a, expected));
+        }
+    }
+
+    for f in all_flags() {
+        for carry in [false, true] {
+            let mut out = f;
+            alu::set_carry(carry, &mut out);
+            assert_eq!(out, flags(f.z(), false, false, carry));
+        }
+    }
+}
@@ -0,0 +1,93 @@
+// Runs every blargg rom dropped into tests/roms/blargg, or the directory in RGBC_BLARGG_DIR,
+// and checks the runner itself against small roms that print over serial
+
+use std::fs;
+use std::path::PathBuf;
+use rgbc::blargg::{run, BlarggStatus};
+use rgbc::rom::Rom;
+
+// About 48 seconds of emulated time, the slowest cpu_instrs rom needs less than half
+const CYCLE_BUDGET: u64 = 200_000_000;
+
+// Prints the zero terminated message at 0x0111 over serial, then loops forever
+fn printing_rom(message: &str) -> Rom {
+    let mut data = vec![0; 0x8000];
+    let program = [
+        0x21, 0x11, 0x01, // ld hl, $0111
+        0x2A,             // ld a, [hl+]
+        0xB7,             // or a, a
+        0x28, 0x08,       // jr z, $010f
+        0xE0, 0x01,       // ldh [$ff01], a
+        0x3E, 0x81,       // ld a, $81
+        0xE0, 0x02,       // ldh [$ff02], a
+        0x18, 0xF4,       // jr $0103
+        0x18, 0xFE,       // jr $010f
+    ];
+    data[0x100..0x111].copy_from_slice(&program);
+    data[0x111..0x111 + message.len()].copy_from_slice(message.as_bytes());
+    Rom { data }
+}
+
+#[test]
+fn reports_serial_verdict() {
+    let result = run(printing_rom("01-special\n\nPassed\n"), CYCLE_BUDGET).unwrap();
+    assert_eq!((result.status, result.output.as_str()), (BlarggStatus::Passed, "01-special\n\nPassed\n"));
+
+    let result = run(printing_rom("Failed #3\n"), CYCLE_BUDGET).unwrap();
+    assert_eq!((result.status, result.output.as_str()), (BlarggStatus::Failed, "Failed #3\n"));
+
+    let result = run(printing_rom("Running"), 1_000_000).unwrap();
+    assert_eq!(result.status, BlarggStatus::TimedOut);
+    assert!(result.cycles >= 1_000_000);
+}
+
+#[test]
+fn stop_ends_the_run() {
+    let mut data = vec![0; 0x8000];
+    data[0x100..0x103].copy_from_slice(&[0x10, 0x00, 0x00]); // stop
+    let result = run(Rom { data }, 1_000_000).unwrap();
+    assert_eq!((result.status, result.output.as_str()), (BlarggStatus::TimedOut, ""));
+    assert!(result.cycles < 1_000_000);
+}
+
+#[test]
+fn header_picks_the_model() {
+    // Prints A as the boot rom left it
+    let mut data = vec![0; 0x8000];
+    data[0x100..0x108].copy_from_slice(&[0xE0, 0x01, 0x3E, 0x81, 0xE0, 0x02, 0x18, 0xFE]);
+    let result = run(Rom { data: data.clone() }, 1_000_000).unwrap();
+    assert_eq!(result.output, "\u{1}");
+
+    data[0x143] = 0x80;
+    let result = run(Rom { data }, 1_000_000).unwrap();
+    assert_eq!(result.output, "\u{11}");
+}
+
+#[test]
+fn blargg_roms() {
+    let dir = std::env::var_os("RGBC_BLARGG_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/roms/blargg"));
+    let Ok(entries) = fs::read_dir(&dir) else {
+        eprintln!("Skipping blargg roms, {} not found", dir.display());
+        return;
+    };
+
+    let mut paths: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|extension| extension == "gb"))
+        .collect();
+    paths.sort();
+
+    let mut failures = Vec::new();
+    for path in paths {
+        let rom = Rom::new(&path).expect("Failed to read rom");
+        let name = path.file_name().unwrap().to_string_lossy().into_owned();
+        match run(rom, CYCLE_BUDGET) {
+            Ok(result) if result.status == BlarggStatus::Passed => println!("{name}: passed"),
+            Ok(result) => failures.push(format!("{name}: {:?} after {} cycles\n{}", result.status, result.cycles, result.output)),
+            Err(e) => failures.push(format!("{name}: {e}")),
+        }
+    }
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+}
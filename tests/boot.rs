@@ -0,0 +1,127 @@
+// Checks the state left behind when a cartridge starts without a boot rom
+
+use rgbc::cpu::Cpu;
+use rgbc::header::NINTENDO_LOGO;
+use rgbc::memory::Memory;
+use rgbc::model::Model;
+use rgbc::palettes::PaletteRam;
+use rgbc::rom::Rom;
+
+fn booted(model: Model, cgb_flag: u8, header_checksum: u8) -> Cpu {
+    let mut data = vec![0; 0x8000];
+    data[0x0104..0x0134].copy_from_slice(&NINTENDO_LOGO);
+    data[0x0143] = cgb_flag;
+    data[0x014D] = header_checksum;
+    let mut cpu = Cpu::new(Memory::new(Rom { data }));
+    cpu.skip_boot_rom(model);
+    cpu
+}
+
+fn registers(cpu: &Cpu) -> [u16; 6] {
+    let regs = cpu.registers();
+    [regs.af(), regs.bc(), regs.de(), regs.hl(), regs.sp, cpu.pc()]
+}
+
+#[test]
+fn registers_per_model() {
+    let cases = [
+        (Model::Dmg, 0x00, 0x00, [0x0180, 0x0013, 0x00D8, 0x014D]),
+        (Model::Dmg, 0x00, 0x3C, [0x01B0, 0x0013, 0x00D8, 0x014D]),
+        (Model::Mgb, 0x00, 0x3C, [0xFFB0, 0x0013, 0x00D8, 0x014D]),
+        (Model::Cgb, 0x80, 0x3C, [0x1180, 0x0000, 0xFF56, 0x000D]),
+        (Model::Cgb, 0x00, 0x3C, [0x1180, 0x0000, 0x0008, 0x007C]),
+        (Model::Agb, 0x80, 0x3C, [0x1100, 0x0100, 0xFF56, 0x000D]),
+    ];
+    for (model, cgb_flag, checksum, [af, bc, de, hl]) in cases {
+        let cpu = booted(model, cgb_flag, checksum);
+        assert_eq!(registers(&cpu), [af, bc, de, hl, 0xFFFE, 0x0100], "{model:?} {cgb_flag:#04x}");
+    }
+}
+
+#[test]
+fn io_registers() {
+    let cpu = booted(Model::Dmg, 0x00, 0x00);
+    let mem = &cpu.mem;
+    let io = [0xFF04, 0xFF07, 0xFF0F, 0xFF26, 0xFF40, 0xFF41, 0xFF47, 0xFF50].map(|addr| mem.read_addr8(addr));
+    assert_eq!(io, [0xAB, 0xF8, 0xE1, 0xF1, 0x91, 0x85, 0xFC, 0xFF]);
+    assert!(!mem.is_boot_rom_mapped());
+}
+
+#[test]
+fn cgb_io_registers() {
+    let cpu = booted(Model::Cgb, 0x80, 0x00);
+    let mem = &cpu.mem;
+    let io = [0xFF02, 0xFF04, 0xFF46, 0xFF4D].map(|addr| mem.read_addr8(addr));
+    assert_eq!(io, [0x7F, 0x1E, 0x00, 0x7E]);
+
+    let cpu = booted(Model::Dmg, 0x00, 0x00);
+    let io = [0xFF02, 0xFF46, 0xFF4D].map(|addr| cpu.mem.read_addr8(addr));
+    assert_eq!(io, [0x7E, 0xFF, 0xFF]);
+}
+
+#[test]
+fn logo_in_vram() {
+    for model in [Model::Dmg, Model::Mgb, Model::Cgb, Model::Agb] {
+        let cpu = booted(model, 0x00, 0x00);
+        let mem = &cpu.mem;
+        // 0xCE doubles into 0xF0 0xFC, each line written twice with an empty second plane
+        let tile: Vec<u8> = (0x8010..0x8018).map(|addr| mem.read_addr8(addr)).collect();
+        assert_eq!(tile, [0xF0, 0x00, 0xF0, 0x00, 0xFC, 0x00, 0xFC, 0x00], "{model:?}");
+        assert_eq!((mem.read_addr8(0x8190), mem.read_addr8(0x8192)), (0x3C, 0x42), "{model:?}");
+
+        let map: Vec<u8> = (0x9904..=0x9910).chain(0x9924..=0x992F).map(|addr| mem.read_addr8(addr)).collect();
+        assert_eq!(map, (1..=12).chain([0x19]).chain(13..=24).collect::<Vec<u8>>(), "{model:?}");
+    }
+}
+
+#[test]
+fn cgb_compatibility_palettes() {
+    let cpu = booted(Model::Cgb, 0x00, 0x00);
+    let colors = (0..4).map(|color| cpu.mem.bg_palettes.color(0, color)).collect::<Vec<_>>();
+    assert_eq!(colors, [0x7FFF, 0x1BEF, 0x6180, 0x0000]);
+    assert_eq!(cpu.mem.obj_palettes.color(1, 1), 0x421F);
+
+    let cpu = booted(Model::Cgb, 0x80, 0x00);
+    assert_eq!(cpu.mem.bg_palettes.color(7, 3), 0x7FFF);
+}
+
+// Nintendo published dmg cartridge with the title
+fn titled(title: &str, old_licensee: u8, new_licensee: &[u8; 2]) -> Cpu {
+    let mut data = vec![0; 0x8000];
+    data[0x0134..0x0134 + title.len()].copy_from_slice(title.as_bytes());
+    data[0x0144..0x0146].copy_from_slice(new_licensee);
+    data[0x014B] = old_licensee;
+    let mut cpu = Cpu::new(Memory::new(Rom { data }));
+    cpu.skip_boot_rom(Model::Cgb);
+    cpu
+}
+
+// Background, first and second object palettes
+fn compatibility_colors(cpu: &Cpu) -> [[u16; 4]; 3] {
+    let mem = &cpu.mem;
+    let palette = |palettes: &PaletteRam, palette| std::array::from_fn(|color| palettes.color(palette, color));
+    [palette(&mem.bg_palettes, 0), palette(&mem.obj_palettes, 0), palette(&mem.obj_palettes, 1)]
+}
+
+#[test]
+fn compatibility_palettes_follow_the_title() {
+    let red = [0x7FFF, 0x421F, 0x1CF2, 0x0000];
+    let green = [0x7FFF, 0x1BEF, 0x0200, 0x0000];
+    let blue = [0x7FFF, 0x7E8C, 0x7C00, 0x0000];
+    // Title checksum 0x14
+    assert_eq!(compatibility_colors(&titled("POKEMON RED", 0x01, b"\0\0")), [red, green, red]);
+    assert_eq!(compatibility_colors(&titled("POKEMON RED", 0x33, b"01")), [red, green, red]);
+
+    // Checksum 0x61 is shared, the fourth letter picks the game
+    assert_eq!(compatibility_colors(&titled("POKEMON BLUE", 0x01, b"\0\0")), [blue, red, blue]);
+    let default = [[0x7FFF, 0x1BEF, 0x6180, 0x0000], red, red];
+    assert_eq!(compatibility_colors(&titled("POKMEON BLUE", 0x01, b"\0\0")), default);
+
+    // The object palettes start a color early, at the black of the palette before
+    let sky = [0x7ED6, 0x4BFF, 0x2175, 0x0000];
+    let shifted = [0x0000, 0x7FFF, 0x421F, 0x1CF2];
+    assert_eq!(compatibility_colors(&titled("SUPER MARIOLAND", 0x01, b"\0\0")), [sky, shifted, shifted]);
+
+    // Other publishers get the default palettes
+    assert_eq!(compatibility_colors(&titled("POKEMON RED", 0x33, b"08")), default);
+}
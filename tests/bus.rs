@@ -0,0 +1,51 @@
+// Runs the cpu over the test buses and checks the order of bus activity
+
+use rgbc::bus::{Access, Bus, FlatBus, RecordingBus};
+use rgbc::cpu::Cpu;
+
+fn recording_cpu(program: &[u8]) -> Cpu<RecordingBus<FlatBus>> {
+    let mut bus = FlatBus::new();
+    bus.data[..program.len()].copy_from_slice(program);
+    let mut cpu = Cpu::new(RecordingBus::new(bus));
+    cpu.registers_mut().sp = 0xD000;
+    cpu.registers_mut().hl_w(0xC000);
+    cpu
+}
+
+#[test]
+fn records_accesses_in_order() {
+    // LD [HL],$5A
+    let mut cpu = recording_cpu(&[0x36, 0x5A]);
+    assert_eq!(cpu.step().unwrap(), 12);
+    assert_eq!(cpu.mem.accesses, [
+        Access::Tick, Access::Read { addr: 0x0000, value: 0x36 },
+        Access::Tick, Access::Read { addr: 0x0001, value: 0x5A },
+        Access::Tick, Access::Write { addr: 0xC000, value: 0x5A },
+    ]);
+}
+
+#[test]
+fn push_has_an_internal_cycle_before_the_writes() {
+    // LD BC,$1234 then PUSH BC
+    let mut cpu = recording_cpu(&[0x01, 0x34, 0x12, 0xC5]);
+    cpu.step().unwrap();
+    cpu.mem.accesses.clear();
+
+    assert_eq!(cpu.step().unwrap(), 16);
+    assert_eq!(cpu.mem.accesses, [
+        Access::Tick, Access::Read { addr: 0x0003, value: 0xC5 },
+        Access::Tick,
+        Access::Tick, Access::Write { addr: 0xCFFF, value: 0x12 },
+        Access::Tick, Access::Write { addr: 0xCFFE, value: 0x34 },
+    ]);
+    assert_eq!(cpu.mem.peek(0xCFFE), 0x34);
+}
+
+#[test]
+fn flat_bus_reaches_every_address() {
+    // LD A,$77 then LD [$FFFF],A, which is plain ram on the flat bus
+    let mut cpu = recording_cpu(&[0x3E, 0x77, 0xEA, 0xFF, 0xFF]);
+    cpu.step().unwrap();
+    cpu.step().unwrap();
+    assert_eq!(cpu.mem.inner.data[0xFFFF], 0x77);
+}
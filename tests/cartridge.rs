@@ -0,0 +1,334 @@
+// Checks bank switching of every mapper against roms whose banks start with their own number
+
+use std::cell::Cell;
+use std::rc::Rc;
+use rgbc::cartridge::{Mapper, Mbc1, Mbc2, Mbc3, Mbc5};
+use rgbc::header::NINTENDO_LOGO;
+use rgbc::rtc::{Clock, CycleClock, Rtc, SAVE_STATE_SIZE};
+
+fn banked_rom(banks: usize) -> Vec<u8> {
+    let mut rom = vec![0; banks * 0x4000];
+    for bank in 0..banks {
+        rom[bank * 0x4000] = bank as u8;
+    }
+    rom
+}
+
+fn enable_ram(mapper: &mut dyn Mapper) {
+    mapper.write_rom(0x0000, 0x0A);
+}
+
+#[test]
+fn mbc1_rom_banks() {
+    let mut mbc = Mbc1::new(banked_rom(128), 0);
+    assert_eq!((mbc.read_rom(0x0000), mbc.read_rom(0x4000)), (0, 1));
+
+    mbc.write_rom(0x2000, 0x05);
+    assert_eq!(mbc.read_rom(0x4000), 5);
+    // Bank 0 maps bank 1, and so does 0x20 in the upper bits
+    mbc.write_rom(0x2000, 0x00);
+    assert_eq!(mbc.read_rom(0x4000), 1);
+    mbc.write_rom(0x4000, 0x01);
+    assert_eq!((mbc.read_rom(0x4000), mbc.rom_bank(0x4000)), (0x21, 0x21));
+
+    // Upper bits reach 0x0000-0x3FFF only in advanced mode
+    assert_eq!(mbc.read_rom(0x0000), 0);
+    mbc.write_rom(0x6000, 0x01);
+    assert_eq!(mbc.read_rom(0x0000), 0x20);
+
+    // Banks past the end of the rom wrap
+    let mut mbc = Mbc1::new(banked_rom(4), 0);
+    mbc.write_rom(0x2000, 0x06);
+    assert_eq!(mbc.read_rom(0x4000), 2);
+}
+
+#[test]
+fn mbc1_ram() {
+    let mut mbc = Mbc1::new(banked_rom(4), 0x8000);
+    mbc.write_ram(0xA000, 0x11);
+    assert_eq!(mbc.read_ram(0xA000), 0xFF);
+
+    enable_ram(&mut mbc);
+    mbc.write_ram(0xA000, 0x11);
+    // Ram banks switch only in advanced mode
+    mbc.write_rom(0x4000, 0x02);
+    assert_eq!(mbc.read_ram(0xA000), 0x11);
+    mbc.write_rom(0x6000, 0x01);
+    assert_eq!(mbc.read_ram(0xA000), 0x00);
+    mbc.write_ram(0xBFFF, 0x22);
+    mbc.write_rom(0x6000, 0x00);
+    assert_eq!((mbc.read_ram(0xA000), mbc.read_ram(0xBFFF)), (0x11, 0x00));
+
+    mbc.write_rom(0x0000, 0x00);
+    assert_eq!(mbc.read_ram(0xA000), 0xFF);
+}
+
+#[test]
+fn mbc1_multicart() {
+    let mut rom = banked_rom(64);
+    assert!(!Mbc1::is_multicart(&rom));
+    rom[0x40104..0x40134].copy_from_slice(&NINTENDO_LOGO);
+    assert!(Mbc1::is_multicart(&rom));
+
+    // The upper bits start at bit 4, bit 4 of the low register is not wired
+    let mut mbc = Mbc1::new(rom, 0);
+    mbc.write_rom(0x4000, 0x01);
+    mbc.write_rom(0x2000, 0x12);
+    assert_eq!(mbc.read_rom(0x4000), 0x12);
+    mbc.write_rom(0x6000, 0x01);
+    assert_eq!(mbc.read_rom(0x0000), 0x10);
+}
+
+// Unix time the test moves by hand
+struct ManualClock(Rc<Cell<u64>>);
+
+impl Clock for ManualClock {
+    fn now(&self) -> u64 { self.0.get() }
+    fn is_unix_time(&self) -> bool { true }
+}
+
+fn mbc3_with_clock() -> (Mbc3, Rc<Cell<u64>>) {
+    mbc3_at(0)
+}
+
+fn mbc3_at(start: u64) -> (Mbc3, Rc<Cell<u64>>) {
+    let time = Rc::new(Cell::new(start));
+    let rtc = Rtc::new(Box::new(ManualClock(time.clone())), 0);
+    let mut mbc = Mbc3::new(banked_rom(128), 0x8000, Some(rtc));
+    enable_ram(&mut mbc);
+    (mbc, time)
+}
+
+fn latch(mbc: &mut Mbc3) {
+    mbc.write_rom(0x6000, 0x00);
+    mbc.write_rom(0x6000, 0x01);
+}
+
+fn read_rtc(mbc: &mut Mbc3) -> [u8; 5] {
+    [0x08, 0x09, 0x0A, 0x0B, 0x0C].map(|register| {
+        mbc.write_rom(0x4000, register);
+        mbc.read_ram(0xA000)
+    })
+}
+
+#[test]
+fn mbc3_banks() {
+    let mut mbc = Mbc3::new(banked_rom(128), 0x8000, None);
+    mbc.write_rom(0x2000, 0x7F);
+    assert_eq!(mbc.read_rom(0x4000), 0x7F);
+    mbc.write_rom(0x2000, 0x00);
+    assert_eq!(mbc.read_rom(0x4000), 1);
+
+    enable_ram(&mut mbc);
+    mbc.write_rom(0x4000, 0x03);
+    mbc.write_ram(0xA000, 0x33);
+    mbc.write_rom(0x4000, 0x00);
+    assert_eq!(mbc.read_ram(0xA000), 0x00);
+    mbc.write_rom(0x4000, 0x03);
+    assert_eq!(mbc.read_ram(0xA000), 0x33);
+    // Without a clock its registers are open bus
+    mbc.write_rom(0x4000, 0x08);
+    assert_eq!(mbc.read_ram(0xA000), 0xFF);
+}
+
+#[test]
+fn mbc3_rtc_latches_on_rising_write() {
+    let (mut mbc, time) = mbc3_with_clock();
+    time.set(61);
+    assert_eq!(read_rtc(&mut mbc), [0; 5]);
+
+    // Writing 1 without a 0 first does not latch
+    mbc.write_rom(0x6000, 0x01);
+    assert_eq!(read_rtc(&mut mbc), [0; 5]);
+    latch(&mut mbc);
+    assert_eq!(read_rtc(&mut mbc), [1, 1, 0, 0, 0]);
+
+    // The latched copy stays while the clock runs on
+    time.set(2 * 86400 + 3 * 3600);
+    assert_eq!(read_rtc(&mut mbc), [1, 1, 0, 0, 0]);
+    latch(&mut mbc);
+    assert_eq!(read_rtc(&mut mbc), [0, 0, 3, 2, 0]);
+}
+
+#[test]
+fn mbc3_rtc_day_carry_and_halt() {
+    let (mut mbc, time) = mbc3_with_clock();
+    time.set(512 * 86400 + 5);
+    latch(&mut mbc);
+    assert_eq!(read_rtc(&mut mbc), [5, 0, 0, 0, 0x80]);
+
+    // Halting stops the clock and clears the carry, writes land in the counters
+    mbc.write_rom(0x4000, 0x0C);
+    mbc.write_ram(0xA000, 0x41);
+    mbc.write_rom(0x4000, 0x08);
+    mbc.write_ram(0xA000, 30);
+    time.set(time.get() + 100);
+    latch(&mut mbc);
+    assert_eq!(read_rtc(&mut mbc), [30, 0, 0, 0, 0x41]);
+
+    mbc.write_rom(0x4000, 0x0C);
+    mbc.write_ram(0xA000, 0x01);
+    time.set(time.get() + 40);
+    latch(&mut mbc);
+    assert_eq!(read_rtc(&mut mbc), [10, 1, 0, 0, 0x01]);
+}
+
+#[test]
+fn mbc3_rtc_follows_emulated_cycles() {
+    // The offset moves the clock ahead from the start
+    let rtc = Rtc::new(Box::new(CycleClock::new()), 3600);
+    let mut mbc = Mbc3::new(banked_rom(4), 0, Some(rtc));
+    enable_ram(&mut mbc);
+    for _ in 0..CycleClock::CYCLES_PER_SECOND / 4 {
+        mbc.tick(4);
+    }
+    latch(&mut mbc);
+    assert_eq!(read_rtc(&mut mbc), [1, 0, 1, 0, 0]);
+}
+
+#[test]
+fn mbc3_rtc_is_saved_after_the_ram() {
+    let (mut mbc, time) = mbc3_at(1_700_000_000);
+    mbc.write_ram(0xA000, 0x5A);
+    time.set(time.get() + 86400 + 3661);
+    latch(&mut mbc);
+    let save = mbc.save_data();
+    assert_eq!(save.len(), 0x8000 + SAVE_STATE_SIZE);
+
+    // A minute passed while the emulator was closed, the latched registers come back as they were
+    let (mut loaded, _) = mbc3_at(1_700_000_000 + 86400 + 3661 + 60);
+    loaded.load_save_data(&save);
+    assert_eq!(loaded.read_ram(0xA000), 0x5A);
+    assert_eq!(read_rtc(&mut loaded), [1, 1, 1, 1, 0]);
+    latch(&mut loaded);
+    assert_eq!(read_rtc(&mut loaded), [1, 2, 1, 1, 0]);
+
+    // A timestamp past the current time counts as no time passed
+    let (mut loaded, _) = mbc3_at(0);
+    loaded.load_save_data(&save);
+    latch(&mut loaded);
+    assert_eq!(read_rtc(&mut loaded), [1, 1, 1, 1, 0]);
+}
+
+#[test]
+fn mbc3_rtc_offset_only_applies_without_a_save() {
+    let (mut mbc, time) = mbc3_at(1_700_000_000);
+    time.set(time.get() + 61);
+    latch(&mut mbc);
+    let save = mbc.save_data();
+
+    // Reloading with an offset keeps the saved counters
+    let rtc = Rtc::new(Box::new(ManualClock(Rc::new(Cell::new(1_700_000_061)))), 3600);
+    let mut loaded = Mbc3::new(banked_rom(128), 0x8000, Some(rtc));
+    enable_ram(&mut loaded);
+    loaded.load_save_data(&save);
+    latch(&mut loaded);
+    assert_eq!(read_rtc(&mut loaded), [1, 1, 0, 0, 0]);
+}
+
+#[test]
+fn mbc3_rtc_ignores_emulated_time_from_another_clock() {
+    let rtc = Rtc::new(Box::new(CycleClock::new()), 0);
+    let mut mbc = Mbc3::new(banked_rom(128), 0x8000, Some(rtc));
+    enable_ram(&mut mbc);
+    for _ in 0..CycleClock::CYCLES_PER_SECOND / 4 {
+        mbc.tick(4);
+    }
+    latch(&mut mbc);
+    let save = mbc.save_data();
+
+    // The cycle clock's reading is no unix time, no time passed since it was saved
+    let (mut loaded, _) = mbc3_at(1_700_000_000);
+    loaded.load_save_data(&save);
+    latch(&mut loaded);
+    assert_eq!(read_rtc(&mut loaded), [1, 0, 0, 0, 0]);
+}
+
+#[test]
+fn mbc5_banks() {
+    let mut mbc = Mbc5::new(banked_rom(512), 0x20000, false);
+    // Bank 0 can be mapped at 0x4000
+    mbc.write_rom(0x2000, 0x00);
+    assert_eq!(mbc.read_rom(0x4000), 0);
+    mbc.write_rom(0x2000, 0x34);
+    mbc.write_rom(0x3000, 0x01);
+    assert_eq!((mbc.read_rom(0x4000), mbc.rom_bank(0x4000)), (0x34, 0x134));
+
+    // Only 0x0A enables ram, not just its low nibble
+    mbc.write_rom(0x0000, 0x1A);
+    mbc.write_ram(0xA000, 0x11);
+    assert_eq!(mbc.read_ram(0xA000), 0xFF);
+    enable_ram(&mut mbc);
+    mbc.write_rom(0x4000, 0x0F);
+    mbc.write_ram(0xA000, 0x0F);
+    mbc.write_rom(0x4000, 0x00);
+    assert_eq!(mbc.read_ram(0xA000), 0x00);
+    mbc.write_rom(0x4000, 0x0F);
+    assert_eq!(mbc.read_ram(0xA000), 0x0F);
+    assert_eq!(mbc.take_rumble(), None);
+}
+
+#[test]
+fn mbc5_rumble() {
+    let mut mbc = Mbc5::new(banked_rom(4), 0x8000, true);
+    enable_ram(&mut mbc);
+    mbc.write_ram(0xA000, 0x11);
+
+    // Bit 3 drives the motor instead of selecting a ram bank
+    mbc.write_rom(0x4000, 0x08);
+    assert_eq!(mbc.read_ram(0xA000), 0x11);
+    assert_eq!(mbc.take_rumble(), Some(true));
+    assert_eq!(mbc.take_rumble(), None);
+    mbc.write_rom(0x4000, 0x08);
+    assert_eq!(mbc.take_rumble(), None);
+    mbc.write_rom(0x4000, 0x00);
+    assert_eq!(mbc.take_rumble(), Some(false));
+}
+
+#[test]
+fn mbc2_registers_by_address_bit_8() {
+    let mut mbc = Mbc2::new(banked_rom(16));
+    mbc.write_rom(0x0100, 0x0A);
+    assert_eq!(mbc.read_rom(0x4000), 10);
+    mbc.write_rom(0x3F00, 0x00);
+    assert_eq!(mbc.read_rom(0x4000), 1);
+
+    // With bit 8 set 0x0A selects a bank, with it clear it enables ram
+    mbc.write_rom(0x0300, 0x0A);
+    mbc.write_ram(0xA000, 0x5C);
+    assert_eq!((mbc.read_ram(0xA000), mbc.rom_bank(0x4000)), (0xFF, 10));
+    mbc.write_rom(0x3E00, 0x0A);
+    mbc.write_ram(0xA000, 0x5C);
+    assert_eq!((mbc.read_ram(0xA000), mbc.rom_bank(0x4000)), (0xFC, 10));
+}
+
+#[test]
+fn mbc2_half_byte_ram() {
+    let mut mbc = Mbc2::new(banked_rom(4));
+    enable_ram(&mut mbc);
+    mbc.write_ram(0xA1FF, 0xA7);
+    // Mirrored every 512 bytes up to 0xBFFF
+    assert_eq!((mbc.read_ram(0xA3FF), mbc.read_ram(0xBFFF)), (0xF7, 0xF7));
+
+    let save = mbc.save_data();
+    assert_eq!((save.len(), save[0x1FF]), (0x200, 0x07));
+    let mut loaded = Mbc2::new(banked_rom(4));
+    loaded.load_save_data(&[0xFF; 0x200]);
+    enable_ram(&mut loaded);
+    assert_eq!(loaded.save_data(), vec![0x0F; 0x200]);
+    assert_eq!(loaded.read_ram(0xA000), 0xFF);
+}
+
+#[test]
+fn save_data_round_trips() {
+    let mut mbc = Mbc5::new(banked_rom(4), 0x8000, false);
+    enable_ram(&mut mbc);
+    mbc.write_rom(0x4000, 0x03);
+    mbc.write_ram(0xBFFF, 0x42);
+
+    let mut loaded = Mbc5::new(banked_rom(4), 0x8000, false);
+    loaded.load_save_data(&mbc.save_data());
+    enable_ram(&mut loaded);
+    loaded.write_rom(0x4000, 0x03);
+    assert_eq!(loaded.read_ram(0xBFFF), 0x42);
+}
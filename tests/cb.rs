@@ -0,0 +1,130 @@
+// Checks the CB prefixed rotates, shifts and bit operations on every operand, their flags
+// and their timing
+
+use rgbc::cpu::Cpu;
+use rgbc::flags::Flags;
+use rgbc::memory::Memory;
+use rgbc::rom::Rom;
+
+const HL: u16 = 0xC000;
+
+// Runs CB op with the operand the low three bits select set to value
+fn run(op: u8, value: u8, flags: u8) -> (Cpu, u8) {
+    let mut cpu = Cpu::new(Memory::new(Rom { data: vec![0xCB, op] }));
+    cpu.registers_mut().hl_w(HL);
+    cpu.registers_mut().f = Flags::from_bits(flags);
+    write_operand(&mut cpu, op, value);
+    let cycles = cpu.step().unwrap();
+    assert_eq!(cpu.pc(), 0x0002);
+    (cpu, cycles)
+}
+
+fn write_operand(cpu: &mut Cpu, op: u8, value: u8) {
+    let regs = cpu.registers_mut();
+    match op & 0x07 {
+        0 => regs.b = value,
+        1 => regs.c = value,
+        2 => regs.d = value,
+        3 => regs.e = value,
+        4 => regs.h = value,
+        5 => regs.l = value,
+        6 => cpu.mem.write_addr8(HL, value),
+        _ => regs.a = value,
+    }
+}
+
+fn read_operand(cpu: &Cpu, op: u8) -> u8 {
+    let regs = cpu.registers();
+    match op & 0x07 {
+        0 => regs.b,
+        1 => regs.c,
+        2 => regs.d,
+        3 => regs.e,
+        4 => regs.h,
+        5 => regs.l,
+        6 => cpu.mem.read_addr8(HL),
+        _ => regs.a,
+    }
+}
+
+// H and L also hold the address of (HL), they are checked on their own
+fn operands() -> impl Iterator<Item = u8> {
+    (0..8u8).filter(|operand| *operand != 4 && *operand != 5)
+}
+
+#[test]
+fn rotates_and_shifts() {
+    // Opcode row, operand value, incoming flags, result, flags
+    let cases = [
+        (0x00, 0x85, 0x00, 0x0B, 0x10), // RLC
+        (0x00, 0x00, 0xF0, 0x00, 0x80),
+        (0x08, 0x01, 0x00, 0x80, 0x10), // RRC
+        (0x10, 0x80, 0x10, 0x01, 0x10), // RL
+        (0x10, 0x80, 0x00, 0x00, 0x90),
+        (0x18, 0x01, 0x00, 0x00, 0x90), // RR
+        (0x18, 0x02, 0x10, 0x81, 0x00),
+        (0x20, 0xFF, 0x00, 0xFE, 0x10), // SLA
+        (0x28, 0x81, 0x00, 0xC0, 0x10), // SRA
+        (0x30, 0xF1, 0xF0, 0x1F, 0x00), // SWAP
+        (0x30, 0x00, 0x00, 0x00, 0x80),
+        (0x38, 0x81, 0x00, 0x40, 0x10), // SRL
+        (0x38, 0x01, 0x00, 0x00, 0x90),
+    ];
+
+    for (row, value, flags, result, expected) in cases {
+        for operand in operands() {
+            let op = row | operand;
+            let (cpu, cycles) = run(op, value, flags);
+            let context = format!("CB {op:02X} on {value:#04X}");
+            assert_eq!(read_operand(&cpu, op), result, "{context}");
+            assert_eq!(cpu.registers().f.bits(), expected, "{context}");
+            assert_eq!(cycles, if operand == 6 { 16 } else { 8 }, "{context}");
+        }
+    }
+}
+
+#[test]
+fn bit_tests_without_changing_the_operand_or_carry() {
+    for bit in 0..8u8 {
+        for operand in operands() {
+            let op = 0x40 | bit << 3 | operand;
+            for carry in [0x00, 0x10] {
+                let (cpu, cycles) = run(op, !(1 << bit), carry);
+                assert_eq!(read_operand(&cpu, op), !(1 << bit), "CB {op:02X}");
+                assert_eq!(cpu.registers().f.bits(), 0xA0 | carry, "CB {op:02X}");
+                assert_eq!(cycles, if operand == 6 { 12 } else { 8 }, "CB {op:02X}");
+
+                let (cpu, _) = run(op, 1 << bit, carry);
+                assert_eq!(cpu.registers().f.bits(), 0x20 | carry, "CB {op:02X}");
+            }
+        }
+    }
+}
+
+#[test]
+fn res_and_set_leave_the_flags_alone() {
+    for bit in 0..8u8 {
+        for operand in operands() {
+            let res = 0x80 | bit << 3 | operand;
+            let (cpu, cycles) = run(res, 0xFF, 0xF0);
+            assert_eq!(read_operand(&cpu, res), !(1 << bit), "CB {res:02X}");
+            assert_eq!(cpu.registers().f.bits(), 0xF0, "CB {res:02X}");
+            assert_eq!(cycles, if operand == 6 { 16 } else { 8 }, "CB {res:02X}");
+
+            let set = 0xC0 | bit << 3 | operand;
+            let (cpu, cycles) = run(set, 0x00, 0x00);
+            assert_eq!(read_operand(&cpu, set), 1 << bit, "CB {set:02X}");
+            assert_eq!(cpu.registers().f.bits(), 0x00, "CB {set:02X}");
+            assert_eq!(cycles, if operand == 6 { 16 } else { 8 }, "CB {set:02X}");
+        }
+    }
+}
+
+#[test]
+fn h_and_l_operands() {
+    // SWAP H, SRL L
+    let (cpu, _) = run(0x34, 0x12, 0x00);
+    assert_eq!(cpu.registers().h, 0x21);
+    let (cpu, _) = run(0x3D, 0x02, 0x00);
+    assert_eq!((cpu.registers().l, cpu.registers().f.bits()), (0x01, 0x00));
+}
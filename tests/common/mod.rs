@@ -0,0 +1,22 @@
+// Fixtures shared by the cpu tests, not every test file uses all of them
+#![allow(dead_code)]
+
+use rgbc::cpu::Cpu;
+use rgbc::interrupts::{IE_ADDR, IF_ADDR};
+use rgbc::memory::Memory;
+use rgbc::rom::Rom;
+
+// Runs the program from 0x0100 with interrupts disabled and the stack in work ram
+pub fn cpu(program: &[u8]) -> Cpu {
+    let mut data = vec![0; 0x8000];
+    data[0x0100..0x0100 + program.len()].copy_from_slice(program);
+    let mut cpu = Cpu::new(Memory::new(Rom { data }));
+    cpu.set_pc(0x0100);
+    cpu.registers_mut().sp = 0xD000;
+    cpu
+}
+
+pub fn request(cpu: &mut Cpu, enable: u8, flags: u8) {
+    cpu.mem.write_addr8(IE_ADDR, enable);
+    cpu.mem.write_addr8(IF_ADDR, flags);
+}
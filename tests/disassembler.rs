@@ -0,0 +1,102 @@
+// Decodes every opcode of both pages, checks the length and bytes against the decode table,
+// and assembles the text back into the same bytes
+
+use rgbc::bus::{Access, Bus, FlatBus, RecordingBus};
+use rgbc::decode_table::DecodeTable;
+use rgbc::disassembler::{assemble, disassemble, Disassembly};
+use rgbc::memory::Memory;
+use rgbc::rom::Rom;
+
+const ADDR: u16 = 0xC000;
+
+// Operand bytes covering zero, positive and negative offsets and both halves of an address
+const OPERANDS: [[u8; 2]; 4] = [[0x00, 0x00], [0x34, 0x12], [0x80, 0xFF], [0x7F, 0xC0]];
+
+fn decode(program: &[u8], addr: u16) -> Disassembly {
+    let mut mem = Memory::new(Rom { data: Vec::new() });
+    for (i, byte) in program.iter().enumerate() {
+        mem.write_addr8(addr + i as u16, *byte);
+    }
+    disassemble(&mem, addr)
+}
+
+fn round_trip(program: &[u8]) {
+    let disassembly = decode(program, ADDR);
+    assert_eq!(disassembly.bytes, program[..disassembly.length() as usize], "{disassembly}");
+    assert_eq!(assemble(&disassembly.text, ADDR).as_deref(), Some(disassembly.bytes.as_slice()), "{disassembly}");
+}
+
+#[test]
+fn main_page_round_trips() {
+    let table = DecodeTable::get();
+    for op in (0..=255u8).filter(|op| *op != 0xCB) {
+        let length = table.main(op).unwrap().length;
+        for [low, high] in OPERANDS {
+            // The byte after stop is not an operand and always assembles as zero
+            let program = if op == 0x10 { [op, 0x00, 0x00] } else { [op, low, high] };
+            assert_eq!(decode(&program, ADDR).length(), length, "{op:#04x}");
+            round_trip(&program);
+        }
+    }
+}
+
+#[test]
+fn cb_page_round_trips() {
+    for op in 0..=255u8 {
+        assert_eq!(decode(&[0xCB, op], ADDR).length(), 2, "cb {op:#04x}");
+        round_trip(&[0xCB, op]);
+    }
+}
+
+#[test]
+fn rgbds_syntax() {
+    let cases: [(&[u8], u16, &str); 16] = [
+        (&[0x00], ADDR, "nop"),
+        (&[0x2A], ADDR, "ld a, [hl+]"),
+        (&[0x32], ADDR, "ld [hl-], a"),
+        (&[0x20, 0x4E], 0xC100, "jr nz, $c150"),
+        (&[0x18, 0xFE], 0xC100, "jr $c100"),
+        (&[0xCB, 0x7C], ADDR, "bit 7, h"),
+        (&[0xCB, 0x86], ADDR, "res 0, [hl]"),
+        (&[0x08, 0x00, 0xC0], ADDR, "ld [$c000], sp"),
+        (&[0xEA, 0x34, 0x12], ADDR, "ld [$1234], a"),
+        (&[0xE0, 0x44], ADDR, "ldh [$ff44], a"),
+        (&[0xF2], ADDR, "ldh a, [c]"),
+        (&[0xE8, 0xFC], ADDR, "add sp, -$04"),
+        (&[0xF8, 0x05], ADDR, "ld hl, sp+$05"),
+        (&[0xFF], ADDR, "rst $38"),
+        (&[0x40], ADDR, "ld b, b"),
+        (&[0xD3], ADDR, "db $d3"),
+    ];
+
+    for (program, addr, text) in cases {
+        let disassembly = decode(program, addr);
+        assert_eq!((disassembly.to_string().as_str(), disassembly.bytes.as_slice()), (text, program));
+    }
+}
+
+#[test]
+fn assembles_other_spellings() {
+    assert_eq!(assemble("LD A, [HL+]", ADDR), Some(vec![0x2A]));
+    assert_eq!(assemble("ld a, 255", ADDR), Some(vec![0x3E, 0xFF]));
+    assert_eq!(assemble("ld hl, sp - $03", ADDR), Some(vec![0xF8, 0xFD]));
+    assert_eq!(assemble("jp  $0150", ADDR), Some(vec![0xC3, 0x50, 0x01]));
+    assert_eq!(assemble("ld a, $100", ADDR), None);
+    assert_eq!(assemble("jr $0000", ADDR), None);
+    assert_eq!(assemble("ldh [$c000], a", ADDR), None);
+    assert_eq!(assemble("foo a", ADDR), None);
+}
+
+#[test]
+fn works_on_any_bus() {
+    let mut bus = FlatBus::new();
+    bus.data[0x0150..0x0153].copy_from_slice(&[0xC3, 0x50, 0x01]);
+    assert_eq!(disassemble(&bus, 0x0150).text, "jp $0150");
+
+    // Peeking records no accesses
+    let mut bus = RecordingBus::new(bus);
+    bus.write8(0x0153, 0x76);
+    bus.accesses.clear();
+    assert_eq!(disassemble(&bus, 0x0153).text, "halt");
+    assert_eq!(bus.accesses, Vec::<Access>::new());
+}
@@ -0,0 +1,68 @@
+// Checks the errors raised while loading and in strict mode, and the context they carry
+
+mod common;
+
+use common::cpu;
+use rgbc::error::{EmuError, ErrorContext};
+use rgbc::memory::Memory;
+use rgbc::rom::Rom;
+
+#[test]
+fn boot_rom_size_is_checked() {
+    let mut mem = Memory::new(Rom { data: vec![0; 0x8000] });
+    let error = mem.map_boot_rom(vec![0; 0x200]).unwrap_err();
+    assert_eq!(error, EmuError::InvalidBootRom { size: 0x200 });
+    assert_eq!(error.to_string(), "Invalid boot rom: 512 bytes, expected 256 or 2304");
+    assert!(!mem.is_boot_rom_mapped());
+}
+
+#[test]
+fn strict_mode_faults_on_code_in_io() {
+    // JP $FF10
+    let mut cpu = cpu(&[0xC3, 0x10, 0xFF]);
+    cpu.mem.write_addr8(0xFF10, 0x80);
+    cpu.set_strict(true);
+    cpu.step().unwrap();
+
+    let context = ErrorContext { pc: 0xFF10, opcode: 0x80, bank: 0, cycles: 16 };
+    assert_eq!(cpu.step(), Err(EmuError::BusFault { addr: 0xFF10, context }));
+    // Nothing was fetched
+    assert_eq!(cpu.cycles(), 16);
+}
+
+#[test]
+fn unusable_memory_faults_only_in_strict_mode() {
+    let mut lenient = cpu(&[0xC3, 0xA0, 0xFE]);
+    lenient.step().unwrap();
+    assert_eq!(lenient.step(), Ok(4));
+    assert_eq!(lenient.pc(), 0xFEA1);
+
+    let mut strict = cpu(&[0xC3, 0xA0, 0xFE]);
+    strict.set_strict(true);
+    strict.step().unwrap();
+    assert!(matches!(strict.step(), Err(EmuError::BusFault { addr: 0xFEA0, .. })));
+    // High ram is fine
+    let mut hram = cpu(&[0xC3, 0x80, 0xFF]);
+    hram.set_strict(true);
+    hram.step().unwrap();
+    assert_eq!(hram.step(), Ok(4));
+}
+
+#[test]
+fn context_describes_the_failing_instruction() {
+    // Three NOPs, then an illegal opcode in strict mode
+    let mut cpu = cpu(&[0x00, 0x00, 0x00, 0xE3]);
+    cpu.set_strict(true);
+    for _ in 0..3 {
+        cpu.step().unwrap();
+    }
+
+    let context = ErrorContext { pc: 0x0103, opcode: 0xE3, bank: 0, cycles: 16 };
+    let error = cpu.step().unwrap_err();
+    assert_eq!(error, EmuError::IllegalOpcode(context.clone()));
+    assert_eq!(error.to_string(), "Illegal opcode 0xe3 at 0x0103, rom bank 0, after 16 cycles");
+    assert_eq!(
+        EmuError::BusFault { addr: 0xFF10, context }.to_string(),
+        "Bus fault at 0xff10 executing opcode 0xe3 at 0x0103, rom bank 0, after 16 cycles",
+    );
+}
@@ -0,0 +1,75 @@
+// Checks how HALT and STOP sleep and wake up through Cpu::step
+
+mod common;
+
+use common::{cpu, request};
+use rgbc::cpu::CpuMode;
+use rgbc::interrupts::IF_ADDR;
+use rgbc::joypad::Button;
+
+#[test]
+fn halt_bug_runs_the_next_byte_twice() {
+    // DI, HALT, INC A
+    let mut cpu = cpu(&[0xF3, 0x76, 0x3C]);
+    request(&mut cpu, 0x01, 0x01);
+
+    cpu.step().unwrap();
+    cpu.step().unwrap();
+    assert_eq!(*cpu.mode(), CpuMode::Running);
+    cpu.step().unwrap();
+    cpu.step().unwrap();
+    assert_eq!((cpu.registers().a, cpu.pc()), (2, 0x0103));
+}
+
+#[test]
+fn halt_with_ime_wakes_into_dispatch() {
+    // EI, HALT
+    let mut cpu = cpu(&[0xFB, 0x76]);
+    cpu.step().unwrap();
+    cpu.step().unwrap();
+    assert_eq!(*cpu.mode(), CpuMode::Halted);
+
+    // Halted the cpu idles one m-cycle per step
+    assert_eq!(cpu.step().unwrap(), 4);
+    assert_eq!(cpu.pc(), 0x0102);
+
+    request(&mut cpu, 0x01, 0x01);
+    cpu.step().unwrap();
+    assert_eq!(*cpu.mode(), CpuMode::Running);
+    assert_eq!(cpu.pc(), 0x0040);
+    assert_eq!(cpu.mem.read_addr16(0xCFFE), 0x0102);
+}
+
+#[test]
+fn halt_without_ime_resumes_without_dispatch() {
+    // DI, HALT, INC A
+    let mut cpu = cpu(&[0xF3, 0x76, 0x3C]);
+    cpu.step().unwrap();
+    cpu.step().unwrap();
+    assert_eq!(*cpu.mode(), CpuMode::Halted);
+    cpu.step().unwrap();
+
+    request(&mut cpu, 0x01, 0x01);
+    cpu.step().unwrap();
+    assert_eq!((cpu.registers().a, cpu.pc()), (1, 0x0103));
+    // Still requested, nothing was serviced
+    assert_eq!(cpu.mem.read_addr8(IF_ADDR), 0xE1);
+}
+
+#[test]
+fn stop_wakes_on_a_button_press() {
+    // STOP, INC A
+    let mut cpu = cpu(&[0x10, 0x00, 0x3C]);
+    cpu.step().unwrap();
+    assert_eq!(*cpu.mode(), CpuMode::Stopped);
+
+    // The system clock is stopped as well
+    let cycles = cpu.cycles();
+    assert_eq!(cpu.step().unwrap(), 0);
+    assert_eq!(cpu.cycles(), cycles);
+
+    cpu.mem.set_button(Button::Start, true);
+    cpu.step().unwrap();
+    assert_eq!(*cpu.mode(), CpuMode::Running);
+    assert_eq!((cpu.registers().a, cpu.pc()), (1, 0x0103));
+}
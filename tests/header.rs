@@ -0,0 +1,75 @@
+// Checks header parsing, checksum verification and mapper selection
+
+use rgbc::cartridge;
+use rgbc::error::EmuError;
+use rgbc::header::CartridgeHeader;
+use rgbc::model::Model;
+use rgbc::rom::Rom;
+use rgbc::rtc::{CycleClock, Rtc};
+
+fn rom(title: &[u8], cgb_flag: u8, cartridge_type: u8) -> Vec<u8> {
+    let mut data = vec![0; 0x8000];
+    data[0x0134..0x0134 + title.len()].copy_from_slice(title);
+    data[0x0143] = cgb_flag;
+    data[0x0146] = 0x03;
+    data[0x0147] = cartridge_type;
+    data[0x014A] = 0x01;
+    data[0x014C] = 0x02;
+    data[0x014D] = data[0x0134..=0x014C].iter().fold(0u8, |sum, byte| sum.wrapping_sub(*byte).wrapping_sub(1));
+    let global = data.iter().fold(0u16, |sum, byte| sum.wrapping_add(*byte as u16));
+    data[0x014E..0x0150].copy_from_slice(&global.to_be_bytes());
+    data
+}
+
+fn rtc() -> Rtc {
+    Rtc::new(Box::new(CycleClock::new()), 0)
+}
+
+fn error(rom: Vec<u8>) -> String {
+    match cartridge::load(Rom { data: rom }, rtc()) {
+        Err(EmuError::InvalidRomHeader { reason }) => reason,
+        Err(e) => panic!("unexpected error {e}"),
+        Ok(_) => panic!("loaded"),
+    }
+}
+
+#[test]
+fn parses_fields() {
+    let header = CartridgeHeader::parse(&rom(b"TETRIS", 0x00, 0x00)).unwrap();
+    assert_eq!((header.title.as_str(), header.manufacturer.as_str()), ("TETRIS", ""));
+    assert_eq!((header.sgb_flag, header.destination, header.version), (0x03, 0x01, 0x02));
+    assert_eq!((header.rom_size(), header.ram_size(), header.model()), (Some(0x8000), Some(0), Model::Dmg));
+
+    let header = CartridgeHeader::parse(&rom(b"POKEMON_GLDAAUE", 0x80, 0x00)).unwrap();
+    assert_eq!((header.title.as_str(), header.manufacturer.as_str()), ("POKEMON_GLD", "AAUE"));
+    assert_eq!(header.model(), Model::Cgb);
+
+    assert!(!header.has_battery());
+    assert!(CartridgeHeader::parse(&rom(b"FFL", 0x00, 0x06)).unwrap().has_battery());
+}
+
+#[test]
+fn verifies_checksums() {
+    let mut data = rom(b"TETRIS", 0x00, 0x00);
+    let header = CartridgeHeader::parse(&data).unwrap();
+    assert!(header.is_header_checksum_valid() && header.is_global_checksum_valid());
+
+    data[0x0200] = 0xAA;
+    let header = CartridgeHeader::parse(&data).unwrap();
+    assert!(header.is_header_checksum_valid() && !header.is_global_checksum_valid());
+
+    data[0x0134] = b'X';
+    assert!(!CartridgeHeader::parse(&data).unwrap().is_header_checksum_valid());
+}
+
+#[test]
+fn rejects_truncated_and_unsupported_roms() {
+    assert_eq!(error(vec![0; 0x100]), "rom is 256 bytes, too short for a cartridge header");
+
+    let mut data = rom(b"TETRIS", 0x00, 0x00);
+    data[0x0148] = 0x01;
+    assert_eq!(error(data), "rom is 32768 bytes, the header declares 65536");
+
+    assert_eq!(error(rom(b"TETRIS", 0x00, 0xFC)), "cartridge type 0xfc is not supported");
+    assert!(cartridge::load(Rom { data: rom(b"TETRIS", 0x00, 0x00) }, rtc()).is_ok());
+}
@@ -0,0 +1,55 @@
+// Checks that illegal opcodes lock up the cpu, and report where in strict mode
+
+mod common;
+
+use common::{cpu, request};
+use rgbc::cartridge::Mbc1;
+use rgbc::cpu::{Cpu, CpuMode};
+use rgbc::error::{EmuError, ErrorContext};
+use rgbc::interrupts::IF_ADDR;
+use rgbc::memory::Memory;
+use rgbc::timer::DIV_ADDR;
+
+#[test]
+fn illegal_opcode_locks_up() {
+    let mut cpu = cpu(&[0xD3, 0x3C]);
+    assert_eq!(cpu.step().unwrap(), 4);
+    assert_eq!(*cpu.mode(), CpuMode::Locked);
+
+    // Nothing is fetched anymore, but the rest of the system keeps running
+    for _ in 0..64 {
+        assert_eq!(cpu.step().unwrap(), 4);
+    }
+    assert_eq!((cpu.pc(), cpu.registers().a), (0x0101, 0));
+    assert_eq!(cpu.mem.read_addr8(DIV_ADDR), 1);
+}
+
+#[test]
+fn locked_cpu_ignores_interrupts() {
+    // EI, NOP, illegal
+    let mut cpu = cpu(&[0xFB, 0x00, 0xDD]);
+    cpu.step().unwrap();
+    cpu.step().unwrap();
+    cpu.step().unwrap();
+    request(&mut cpu, 0x01, 0x01);
+
+    cpu.step().unwrap();
+    assert_eq!(*cpu.mode(), CpuMode::Locked);
+    assert_eq!((cpu.pc(), cpu.registers().sp), (0x0103, 0xD000));
+    assert_eq!(cpu.mem.read_addr8(IF_ADDR), 0xE1);
+}
+
+#[test]
+fn strict_mode_reports_pc_and_bank() {
+    // Illegal opcode at the start of bank 2
+    let mut rom = vec![0; 0x10000];
+    rom[0x8000] = 0xFC;
+    let mut cpu = Cpu::new(Memory::with_cartridge(Box::new(Mbc1::new(rom, 0))));
+    cpu.mem.write_addr8(0x2000, 0x02);
+    cpu.set_pc(0x4000);
+    cpu.set_strict(true);
+
+    let context = ErrorContext { pc: 0x4000, opcode: 0xFC, bank: 2, cycles: 4 };
+    assert_eq!(cpu.step(), Err(EmuError::IllegalOpcode(context)));
+    assert_eq!(*cpu.mode(), CpuMode::Locked);
+}
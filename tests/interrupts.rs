@@ -0,0 +1,80 @@
+// Checks interrupt dispatch through Cpu::step
+
+mod common;
+
+use common::{cpu, request};
+use rgbc::interrupts::{IE_ADDR, IF_ADDR};
+
+#[test]
+fn ei_takes_effect_after_the_next_instruction() {
+    // EI, NOP, NOP
+    let mut cpu = cpu(&[0xFB, 0x00, 0x00]);
+    request(&mut cpu, 0x01, 0x01);
+
+    cpu.step().unwrap();
+    assert_eq!(cpu.pc(), 0x0101);
+    cpu.step().unwrap();
+    assert_eq!(cpu.pc(), 0x0102);
+    cpu.step().unwrap();
+    assert_eq!(cpu.pc(), 0x0040);
+}
+
+#[test]
+fn dispatch_takes_20_cycles_and_pushes_pc() {
+    let mut cpu = cpu(&[0xFB, 0x00, 0x00]);
+    cpu.step().unwrap();
+    cpu.step().unwrap();
+    request(&mut cpu, 0x01, 0x01);
+
+    assert_eq!(cpu.step().unwrap(), 20);
+    assert_eq!((cpu.pc(), cpu.registers().sp), (0x0040, 0xCFFE));
+    assert_eq!(cpu.mem.read_addr16(0xCFFE), 0x0102);
+    // Acknowledged and disabled until RETI or EI
+    assert_eq!(cpu.mem.read_addr8(IF_ADDR), 0xE0);
+    request(&mut cpu, 0x01, 0x01);
+    cpu.step().unwrap();
+    assert_eq!(cpu.pc(), 0x0041);
+}
+
+#[test]
+fn lowest_bit_wins() {
+    let mut cpu = cpu(&[0xFB, 0x00, 0x00]);
+    cpu.step().unwrap();
+    cpu.step().unwrap();
+    // Timer and joypad enabled, everything requested
+    request(&mut cpu, 0x14, 0x1F);
+
+    cpu.step().unwrap();
+    assert_eq!(cpu.pc(), 0x0050);
+    assert_eq!(cpu.mem.read_addr8(IF_ADDR), 0xFB);
+}
+
+#[test]
+fn reti_enables_interrupts_without_delay() {
+    // RETI to 0x0200
+    let mut cpu = cpu(&[0xD9]);
+    cpu.mem.write_addr16(0xCFFE, 0x0200);
+    cpu.registers_mut().sp = 0xCFFE;
+    request(&mut cpu, 0x04, 0x04);
+
+    assert_eq!(cpu.step().unwrap(), 16);
+    assert_eq!(cpu.pc(), 0x0200);
+    cpu.step().unwrap();
+    assert_eq!(cpu.pc(), 0x0050);
+}
+
+#[test]
+fn pushing_over_ie_cancels_the_dispatch() {
+    let mut cpu = cpu(&[0xFB, 0x00, 0x00]);
+    cpu.step().unwrap();
+    cpu.step().unwrap();
+    request(&mut cpu, 0x04, 0x04);
+    // The high byte of pc, 0x01, lands in IE and disables the timer interrupt
+    cpu.registers_mut().sp = 0x0000;
+
+    assert_eq!(cpu.step().unwrap(), 20);
+    assert_eq!(cpu.pc(), 0x0000);
+    assert_eq!(cpu.mem.read_addr8(IE_ADDR), 0x01);
+    // Nothing was acknowledged
+    assert_eq!(cpu.mem.read_addr8(IF_ADDR), 0xE4);
+}
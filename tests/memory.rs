@@ -0,0 +1,88 @@
+// Checks that each region of the memory map reads and writes the way its owner does
+
+use rgbc::memory::{Memory, LY_ADDR};
+use rgbc::rom::Rom;
+
+fn memory() -> Memory {
+    let mut data = vec![0; 0x8000];
+    data[0x0100] = 0x12;
+    data[0x7FFF] = 0x34;
+    Memory::new(Rom { data })
+}
+
+#[test]
+fn rom_is_read_only() {
+    let mut mem = memory();
+    mem.write_addr8(0x0100, 0xAA);
+    mem.write_addr8(0x7FFF, 0xAA);
+    assert_eq!((mem.read_addr8(0x0100), mem.read_addr8(0x7FFF)), (0x12, 0x34));
+}
+
+#[test]
+fn echo_ram_mirrors_work_ram() {
+    let mut mem = memory();
+    mem.write_addr8(0xC123, 0x11);
+    assert_eq!(mem.read_addr8(0xE123), 0x11);
+    mem.write_addr8(0xFDFF, 0x22);
+    assert_eq!(mem.read_addr8(0xDDFF), 0x22);
+}
+
+#[test]
+fn ram_regions_keep_their_values() {
+    let mut mem = memory();
+    for (i, addr) in [0x8000, 0x9FFF, 0xA000, 0xBFFF, 0xC000, 0xDFFF, 0xFE00, 0xFE9F, 0xFF80, 0xFFFE].into_iter().enumerate() {
+        mem.write_addr8(addr, i as u8 + 1);
+        assert_eq!(mem.read_addr8(addr), i as u8 + 1, "{addr:#06x}");
+    }
+}
+
+#[test]
+fn unusable_region_ignores_writes() {
+    let mut mem = memory();
+    mem.write_addr8(0xFEA0, 0xAA);
+    mem.write_addr8(0xFEFF, 0xAA);
+    assert_eq!((mem.read_addr8(0xFEA0), mem.read_addr8(0xFEFF)), (0x00, 0x00));
+}
+
+#[test]
+fn interrupt_enable_is_the_last_byte() {
+    let mut mem = memory();
+    mem.write_addr8(0xFFFF, 0x1F);
+    assert_eq!(mem.read_addr8(0xFFFF), 0x1F);
+    assert_eq!(mem.interrupts.read_ie(), 0x1F);
+    assert_eq!(mem.read_addr16(0xFFFE), 0x1F00);
+}
+
+#[test]
+fn boot_rom_overlays_cartridge_until_unmapped() {
+    let mut mem = memory();
+    mem.map_boot_rom(vec![0xBB; 0x100]).unwrap();
+    assert_eq!((mem.read_addr8(0x0000), mem.read_addr8(0x00FF), mem.read_addr8(0x0100)), (0xBB, 0xBB, 0x12));
+
+    // Zero writes leave it mapped
+    mem.write_addr8(0xFF50, 0x00);
+    assert!(mem.is_boot_rom_mapped());
+    mem.write_addr8(0xFF50, 0x01);
+    assert!(!mem.is_boot_rom_mapped());
+    assert_eq!(mem.read_addr8(0x0000), 0x00);
+}
+
+#[test]
+fn cgb_boot_rom_leaves_the_header_visible() {
+    let mut mem = memory();
+    mem.map_boot_rom(vec![0xBB; 0x900]).unwrap();
+    assert_eq!(mem.read_addr8(0x00FF), 0xBB);
+    assert_eq!(mem.read_addr8(0x0100), 0x12);
+    assert_eq!((mem.read_addr8(0x0200), mem.read_addr8(0x08FF)), (0xBB, 0xBB));
+    assert_eq!(mem.read_addr8(0x0900), 0x00);
+}
+
+#[test]
+fn ly_wraps_after_line_153() {
+    let mut mem = memory();
+    for line in 1..=154u16 {
+        mem.tick(228);
+        mem.tick(228);
+        assert_eq!(mem.read_addr8(LY_ADDR) as u16, line % 154);
+    }
+}
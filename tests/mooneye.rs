@@ -0,0 +1,50 @@
+// Checks the breakpoint and register signature detection against small roms
+
+use rgbc::mooneye::{run, MooneyeStatus};
+use rgbc::rom::Rom;
+
+// Loads the registers, hits the breakpoint and loops forever
+fn signature_rom(signature: [u8; 6]) -> Rom {
+    let [b, c, d, e, h, l] = signature;
+    let mut data = vec![0; 0x8000];
+    let program = [
+        0x06, b, 0x0E, c, 0x16, d, 0x1E, e, 0x26, h, 0x2E, l, // ld b..l, n8
+        0x40,       // ld b, b
+        0x18, 0xFE, // jr to itself
+    ];
+    data[0x100..0x100 + program.len()].copy_from_slice(&program);
+    Rom { data }
+}
+
+#[test]
+fn detects_signatures() {
+    let result = run(signature_rom([3, 5, 8, 13, 21, 34]), 1_000_000).unwrap();
+    assert_eq!(result.status, MooneyeStatus::Passed);
+
+    let result = run(signature_rom([0x42; 6]), 1_000_000).unwrap();
+    assert_eq!(result.status, MooneyeStatus::Failed);
+
+    let result = run(signature_rom([1, 2, 3, 4, 5, 6]), 1_000_000).unwrap();
+    assert_eq!(result.status, MooneyeStatus::UnknownSignature([1, 2, 3, 4, 5, 6]));
+}
+
+#[test]
+fn times_out_without_breakpoint() {
+    let mut data = vec![0; 0x8000];
+    data[0x100..0x102].copy_from_slice(&[0x18, 0xFE]);
+    let result = run(Rom { data }, 1_000_000).unwrap();
+    assert_eq!(result.status, MooneyeStatus::TimedOut);
+}
+
+#[test]
+fn header_picks_the_model() {
+    // Breakpoint straight away, the registers hold what the boot rom left
+    let mut data = vec![0; 0x8000];
+    data[0x100] = 0x40;
+    let result = run(Rom { data: data.clone() }, 1_000_000).unwrap();
+    assert_eq!(result.status, MooneyeStatus::UnknownSignature([0x00, 0x13, 0x00, 0xD8, 0x01, 0x4D]));
+
+    data[0x143] = 0x80;
+    let result = run(Rom { data }, 1_000_000).unwrap();
+    assert_eq!(result.status, MooneyeStatus::UnknownSignature([0x00, 0x00, 0xFF, 0x56, 0x00, 0x0D]));
+}
@@ -0,0 +1,43 @@
+// Checks that the low nibble of F always reads as zero and that PUSH AF sees the flags the alu set
+
+mod common;
+
+use common::cpu;
+use rgbc::registers::Registers;
+
+#[test]
+fn af_write_clears_low_nibble_of_f() {
+    let mut regs = Registers::new();
+    regs.af_w(0x12FF);
+    assert_eq!((regs.af(), regs.f.bits()), (0x12F0, 0xF0));
+}
+
+#[test]
+fn pop_af_clears_low_nibble_of_f() {
+    // POP AF
+    let mut cpu = cpu(&[0xF1]);
+    cpu.mem.write_addr16(0xD000, 0x12FF);
+    cpu.step().unwrap();
+    assert_eq!(cpu.registers().af(), 0x12F0);
+    assert_eq!(cpu.registers().sp, 0xD002);
+}
+
+#[test]
+fn push_af_pushes_half_carry() {
+    // LD A,$0F, ADD A,$01, PUSH AF
+    let mut cpu = cpu(&[0x3E, 0x0F, 0xC6, 0x01, 0xF5]);
+    cpu.step().unwrap();
+    cpu.step().unwrap();
+    cpu.step().unwrap();
+    assert_eq!(cpu.mem.read_addr16(0xCFFE), 0x1020);
+}
+
+#[test]
+fn push_af_pushes_zero_and_carry() {
+    // XOR A, SCF, PUSH AF
+    let mut cpu = cpu(&[0xAF, 0x37, 0xF5]);
+    cpu.step().unwrap();
+    cpu.step().unwrap();
+    cpu.step().unwrap();
+    assert_eq!(cpu.mem.read_addr16(0xCFFE), 0x0090);
+}
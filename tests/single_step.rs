@@ -0,0 +1,162 @@
+// Runs the SingleStepTests sm83 suite from tests/roms/sm83, or the directory in RGBC_SST_DIR,
+// executing one instruction per case over a flat bus and diffing the cpu state and ram afterwards.
+// Prints a pass/fail matrix for both opcode pages, run with --release --nocapture to see it.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use rgbc::bus::{Bus, FlatBus};
+use rgbc::cpu::Cpu;
+use rgbc::flags::Flags;
+use serde_json::{json, Value};
+
+#[derive(Default)]
+struct Outcome {
+    passed: usize,
+    failed: usize,
+    first_failure: Option<String>,
+}
+
+fn field(state: &Value, name: &str) -> u16 {
+    state[name].as_u64().unwrap_or_else(|| panic!("missing {name}")) as u16
+}
+
+fn ram(state: &Value) -> impl Iterator<Item = (u16, u8)> + '_ {
+    state["ram"].as_array().expect("missing ram").iter().map(|entry| {
+        (entry[0].as_u64().unwrap() as u16, entry[1].as_u64().unwrap() as u8)
+    })
+}
+
+fn load_state(cpu: &mut Cpu<FlatBus>, state: &Value) {
+    let regs = cpu.registers_mut();
+    regs.a = field(state, "a") as u8;
+    regs.f = Flags::from_bits(field(state, "f") as u8);
+    regs.b = field(state, "b") as u8;
+    regs.c = field(state, "c") as u8;
+    regs.d = field(state, "d") as u8;
+    regs.e = field(state, "e") as u8;
+    regs.h = field(state, "h") as u8;
+    regs.l = field(state, "l") as u8;
+    regs.sp = field(state, "sp");
+    cpu.set_pc(field(state, "pc"));
+
+    for (addr, value) in ram(state) {
+        cpu.mem.write8(addr, value);
+    }
+}
+
+// Every difference between the cpu and the expected state
+fn diff_state(cpu: &Cpu<FlatBus>, state: &Value) -> Vec<String> {
+    let regs = cpu.registers();
+    let actual = [
+        ("a", regs.a as u16), ("f", regs.f.bits() as u16), ("b", regs.b as u16), ("c", regs.c as u16),
+        ("d", regs.d as u16), ("e", regs.e as u16), ("h", regs.h as u16), ("l", regs.l as u16),
+        ("sp", regs.sp), ("pc", cpu.pc()),
+    ];
+
+    let registers = actual.into_iter().filter_map(|(name, value)| {
+        let expected = field(state, name);
+        (value != expected).then(|| format!("{name}: expected {expected:#06x}, got {value:#06x}"))
+    });
+    let memory = ram(state).filter_map(|(addr, expected)| {
+        let value = cpu.mem.peek(addr);
+        (value != expected).then(|| format!("[{addr:#06x}]: expected {expected:#04x}, got {value:#04x}"))
+    });
+    registers.chain(memory).collect()
+}
+
+// None if the case passes, otherwise a description of what went wrong
+fn run_case(case: &Value) -> Option<String> {
+    let mut cpu = Cpu::new(FlatBus::new());
+    load_state(&mut cpu, &case["initial"]);
+
+    let differences = match cpu.step() {
+        Ok(_) => diff_state(&cpu, &case["final"]),
+        Err(e) => vec![e.to_string()],
+    };
+    (!differences.is_empty()).then(|| format!("{}: {}", case["name"], differences.join(", ")))
+}
+
+fn run_file(path: &Path) -> Outcome {
+    let text = fs::read_to_string(path).expect("Failed to read test file");
+    let cases: Vec<Value> = serde_json::from_str(&text).expect("Failed to parse test file");
+
+    let mut outcome = Outcome::default();
+    for case in &cases {
+        match run_case(case) {
+            None => outcome.passed += 1,
+            Some(failure) => {
+                outcome.failed += 1;
+                outcome.first_failure.get_or_insert(failure);
+            }
+        }
+    }
+    outcome
+}
+
+// One cell per opcode, . for passing, X for failing and - without a test file
+fn print_matrix(title: &str, outcomes: &[Option<Outcome>]) {
+    println!("{title}");
+    println!("    0 1 2 3 4 5 6 7 8 9 A B C D E F");
+    for (row, cells) in outcomes.chunks(16).enumerate() {
+        let cells: Vec<&str> = cells.iter().map(|outcome| match outcome {
+            Some(outcome) if outcome.failed == 0 => ".",
+            Some(_) => "X",
+            None => "-",
+        }).collect();
+        println!("{row:X}x  {}", cells.join(" "));
+    }
+}
+
+#[test]
+fn inline_case() {
+    // ADD A,B with a half carry, followed by its expected state
+    let case = json!({
+        "name": "80 inline",
+        "initial": { "pc": 0xC000, "sp": 0xFFFE, "a": 0x0F, "b": 0x01, "c": 0, "d": 0, "e": 0, "f": 0x00,
+                     "h": 0, "l": 0, "ime": 0, "ram": [[0xC000, 0x80]] },
+        "final": { "pc": 0xC001, "sp": 0xFFFE, "a": 0x10, "b": 0x01, "c": 0, "d": 0, "e": 0, "f": 0x20,
+                   "h": 0, "l": 0, "ime": 0, "ram": [[0xC000, 0x80]] },
+        "cycles": [[0xC000, 0x80, "r-m"]],
+    });
+    assert_eq!(run_case(&case), None);
+
+    // Writes land in ram, including 0xFFFF which is plain memory on the test bus
+    let case = json!({
+        "name": "e2 inline",
+        "initial": { "pc": 0x0100, "sp": 0xFFFE, "a": 0x5A, "b": 0, "c": 0xFF, "d": 0, "e": 0, "f": 0xF0,
+                     "h": 0, "l": 0, "ime": 0, "ram": [[0x0100, 0xE2], [0xFFFF, 0x00]] },
+        "final": { "pc": 0x0101, "sp": 0xFFFE, "a": 0x5A, "b": 0, "c": 0xFF, "d": 0, "e": 0, "f": 0xF0,
+                   "h": 0, "l": 0, "ime": 0, "ram": [[0x0100, 0xE2], [0xFFFF, 0x5A]] },
+    });
+    assert_eq!(run_case(&case), None);
+}
+
+#[test]
+fn single_step_tests() {
+    let dir = std::env::var_os("RGBC_SST_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/roms/sm83"));
+    if !dir.is_dir() {
+        eprintln!("Skipping SingleStepTests, {} not found", dir.display());
+        return;
+    }
+
+    let run_page = |prefix: &str| -> Vec<Option<Outcome>> {
+        (0..=255u8)
+            .map(|op| dir.join(format!("{prefix}{op:02x}.json")))
+            .map(|path| path.is_file().then(|| run_file(&path)))
+            .collect()
+    };
+    let main = run_page("");
+    let cb = run_page("cb ");
+    print_matrix("Main page", &main);
+    print_matrix("CB page", &cb);
+
+    let failures: Vec<String> = main.iter().chain(&cb)
+        .flatten()
+        .filter_map(|outcome| outcome.first_failure.as_ref().map(|failure| {
+            format!("{} of {} failed, first {failure}", outcome.failed, outcome.passed + outcome.failed)
+        }))
+        .collect();
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+}
@@ -0,0 +1,76 @@
+// Checks the cgb speed switch and what runs at which speed afterwards
+
+mod common;
+
+use common::cpu;
+use rgbc::bus::Bus;
+use rgbc::cpu::{Cpu, CpuMode};
+use rgbc::memory::{Memory, LY_ADDR};
+use rgbc::model::Model;
+use rgbc::rom::Rom;
+use rgbc::speed::KEY1_ADDR;
+use rgbc::timer::DIV_ADDR;
+
+fn memory(model: Model) -> Memory {
+    let mut mem = Memory::new(Rom { data: vec![0; 0x8000] });
+    mem.set_model(model);
+    mem
+}
+
+// STOP with the switch armed
+fn stop_cpu(model: Model) -> Cpu {
+    let mut cpu = cpu(&[0x10, 0x00, 0x00]);
+    cpu.mem.set_model(model);
+    cpu.mem.write_addr8(KEY1_ADDR, 0x01);
+    cpu.step().unwrap();
+    cpu
+}
+
+fn tick(mem: &mut Memory, cycles: u32) {
+    for _ in 0..cycles / 4 {
+        mem.tick(4);
+    }
+}
+
+#[test]
+fn dmg_has_no_speed_switch() {
+    let cpu = stop_cpu(Model::Dmg);
+    assert_eq!(cpu.mem.read_addr8(KEY1_ADDR), 0xFF);
+    assert!(!cpu.mem.speed.is_double_speed());
+    assert_eq!(*cpu.mode(), CpuMode::Stopped);
+}
+
+#[test]
+fn cgb_stop_switches_speed() {
+    let mut mem = memory(Model::Cgb);
+    assert_eq!(mem.read_addr8(KEY1_ADDR), 0x7E);
+    mem.write_addr8(KEY1_ADDR, 0x01);
+    assert_eq!(mem.read_addr8(KEY1_ADDR), 0x7F);
+
+    // Execution carries on at the new speed with the prepare bit cleared
+    let mut cpu = stop_cpu(Model::Cgb);
+    assert_eq!(*cpu.mode(), CpuMode::Running);
+    assert_eq!(cpu.mem.read_addr8(KEY1_ADDR), 0xFE);
+
+    // Switching back needs the prepare bit again
+    cpu.mem.write_addr8(KEY1_ADDR, 0x01);
+    assert!(cpu.mem.stop());
+    assert_eq!(cpu.mem.read_addr8(KEY1_ADDR), 0x7E);
+}
+
+#[test]
+fn double_speed_halves_lcd_dots_per_cpu_cycle() {
+    // A scanline is 456 dots, one cpu cycle each at normal speed
+    let mut mem = memory(Model::Cgb);
+    tick(&mut mem, 456);
+    assert_eq!((mem.read_addr8(LY_ADDR), mem.read_addr8(DIV_ADDR)), (1, 1));
+
+    // DIV follows the cpu clock, so it counts twice as much per scanline
+    let mut mem = memory(Model::Cgb);
+    mem.write_addr8(KEY1_ADDR, 0x01);
+    assert!(mem.stop());
+    tick(&mut mem, 456);
+    assert_eq!((mem.read_addr8(LY_ADDR), mem.read_addr8(DIV_ADDR)), (0, 1));
+    tick(&mut mem, 456);
+    assert_eq!((mem.read_addr8(LY_ADDR), mem.read_addr8(DIV_ADDR)), (1, 3));
+}
@@ -0,0 +1,107 @@
+// Checks the t-cycles every opcode takes when executed against the reference timings from
+// https://izik1.github.io/gbops/
+
+use rgbc::cpu::Cpu;
+use rgbc::flags::Flags;
+use rgbc::memory::Memory;
+use rgbc::rom::Rom;
+
+// Branch not taken, illegal opcodes lock up after the fetch and 0xCB is the prefix fetch alone
+#[rustfmt::skip]
+const CYCLES: [u8; 256] = [
+//  x0  x1  x2  x3  x4  x5  x6  x7  x8  x9  xA  xB  xC  xD  xE  xF
+     4, 12,  8,  8,  4,  4,  8,  4, 20,  8,  8,  8,  4,  4,  8,  4, // 0x
+     4, 12,  8,  8,  4,  4,  8,  4, 12,  8,  8,  8,  4,  4,  8,  4, // 1x
+     8, 12,  8,  8,  4,  4,  8,  4,  8,  8,  8,  8,  4,  4,  8,  4, // 2x
+     8, 12,  8,  8, 12, 12, 12,  4,  8,  8,  8,  8,  4,  4,  8,  4, // 3x
+     4,  4,  4,  4,  4,  4,  8,  4,  4,  4,  4,  4,  4,  4,  8,  4, // 4x
+     4,  4,  4,  4,  4,  4,  8,  4,  4,  4,  4,  4,  4,  4,  8,  4, // 5x
+     4,  4,  4,  4,  4,  4,  8,  4,  4,  4,  4,  4,  4,  4,  8,  4, // 6x
+     8,  8,  8,  8,  8,  8,  4,  8,  4,  4,  4,  4,  4,  4,  8,  4, // 7x
+     4,  4,  4,  4,  4,  4,  8,  4,  4,  4,  4,  4,  4,  4,  8,  4, // 8x
+     4,  4,  4,  4,  4,  4,  8,  4,  4,  4,  4,  4,  4,  4,  8,  4, // 9x
+     4,  4,  4,  4,  4,  4,  8,  4,  4,  4,  4,  4,  4,  4,  8,  4, // Ax
+     4,  4,  4,  4,  4,  4,  8,  4,  4,  4,  4,  4,  4,  4,  8,  4, // Bx
+     8, 12, 12, 16, 12, 16,  8, 16,  8, 16, 12,  4, 12, 24,  8, 16, // Cx
+     8, 12, 12,  4, 12, 16,  8, 16,  8, 16, 12,  4, 12,  4,  8, 16, // Dx
+    12, 12,  8,  4,  4, 16,  8, 16, 16,  4, 16,  4,  4,  4,  8, 16, // Ex
+    12, 12,  8,  4,  4, 16,  8, 16, 12,  8, 16,  4,  4,  4,  8, 16, // Fx
+];
+
+// Conditional jumps, calls and returns when the branch is taken
+const BRANCH_CYCLES: [(u8, u8); 16] = [
+    (0x20, 12), (0x28, 12), (0x30, 12), (0x38, 12),
+    (0xC2, 16), (0xCA, 16), (0xD2, 16), (0xDA, 16),
+    (0xC4, 24), (0xCC, 24), (0xD4, 24), (0xDC, 24),
+    (0xC0, 20), (0xC8, 20), (0xD0, 20), (0xD8, 20),
+];
+
+// Including the prefix fetch, the second column of each row is (HL)
+fn cb_cycles(opcode: u8) -> u8 {
+    match (opcode, opcode % 8) {
+        (0x40..=0x7F, 6) => 12,
+        (_, 6) => 16,
+        _ => 8,
+    }
+}
+
+// Zero flag set for Z, carry set for C, cleared for NZ and NC
+fn branch_flags(opcode: u8, taken: bool) -> Flags {
+    let set = (opcode & 0x08 != 0) == taken;
+    Flags::from_bits(if set { 0xF0 } else { 0x00 })
+}
+
+// Runs a single instruction at 0x0000 and returns the cycles it took
+fn run(program: &[u8], flags: Flags) -> u8 {
+    run_decoded(program, flags, true)
+}
+
+fn run_decoded(program: &[u8], flags: Flags, pre_decoded: bool) -> u8 {
+    let mut data = vec![0; 0x100];
+    data[..program.len()].copy_from_slice(program);
+
+    let mut cpu = Cpu::new(Memory::new(Rom { data }));
+    cpu.set_pre_decoded(pre_decoded);
+    let regs = cpu.registers_mut();
+    regs.f = flags;
+    regs.sp = 0xD000;
+    regs.hl_w(0xC000);
+
+    cpu.step().unwrap_or_else(|e| panic!("{:#04x} failed: {e}", program[0]))
+}
+
+#[test]
+fn executed_cycles_match_reference() {
+    for op in 0..=255u8 {
+        // Operands point into work ram
+        let cycles = run(&[op, 0x00, 0xC0], branch_flags(op, false));
+        // The prefix runs the following cb instruction, RLC B
+        let expected = if op == 0xCB { cb_cycles(0x00) } else { CYCLES[op as usize] };
+        assert_eq!(cycles, expected, "opcode {op:#04x}");
+    }
+}
+
+#[test]
+fn executed_branch_cycles_match_reference() {
+    for (op, expected) in BRANCH_CYCLES {
+        let cycles = run(&[op, 0x00, 0xC0], branch_flags(op, true));
+        assert_eq!(cycles, expected, "opcode {op:#04x}");
+    }
+}
+
+#[test]
+fn executed_cb_cycles_match_reference() {
+    for op in 0..=255u8 {
+        let cycles = run(&[0xCB, op], Flags::new());
+        assert_eq!(cycles, cb_cycles(op), "opcode 0xcb {op:#04x}");
+    }
+}
+
+#[test]
+fn parsed_instructions_take_the_same_cycles() {
+    for op in 0..=255u8 {
+        let flags = branch_flags(op, true);
+        assert_eq!(run_decoded(&[op, 0x00, 0xC0], flags, false), run(&[op, 0x00, 0xC0], flags), "opcode {op:#04x}");
+        assert_eq!(run_decoded(&[0xCB, op], Flags::new(), false), cb_cycles(op), "opcode 0xcb {op:#04x}");
+    }
+}
@@ -0,0 +1,47 @@
+// Checks the trace lines written before each instruction against the Gameboy Doctor format
+
+use std::fs;
+use rgbc::cpu::Cpu;
+use rgbc::memory::{Memory, LY_ADDR};
+use rgbc::rom::Rom;
+use rgbc::trace::TraceWriter;
+
+#[test]
+fn writes_state_before_each_instruction() {
+    let path = std::env::temp_dir().join(format!("rgbc-trace-{}.log", std::process::id()));
+
+    // LD A,$12 / SCF / NOP
+    let mut data = vec![0; 0x100];
+    data[..4].copy_from_slice(&[0x3E, 0x12, 0x37, 0x00]);
+    let mut cpu = Cpu::new(Memory::new(Rom { data }));
+    cpu.registers_mut().sp = 0xFFFE;
+    cpu.set_trace(Some(TraceWriter::create(&path).unwrap()));
+    for _ in 0..3 {
+        cpu.step().unwrap();
+    }
+    // Dropping the writer flushes it
+    cpu.set_trace(None);
+
+    let trace = fs::read_to_string(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+    assert_eq!(trace, "\
+A:00 F:00 B:00 C:00 D:00 E:00 H:00 L:00 SP:FFFE PC:0000 PCMEM:3E,12,37,00
+A:12 F:00 B:00 C:00 D:00 E:00 H:00 L:00 SP:FFFE PC:0002 PCMEM:37,00,00,00
+A:12 F:10 B:00 C:00 D:00 E:00 H:00 L:00 SP:FFFE PC:0003 PCMEM:00,00,00,00
+");
+}
+
+#[test]
+fn stubbed_ly_reads_0x90() {
+    let mut cpu = Cpu::new(Memory::new(Rom { data: vec![0; 0x100] }));
+    cpu.mem.set_stub_ly(true);
+    for _ in 0..114 * 3 {
+        cpu.mem.tick(4);
+    }
+    assert_eq!(cpu.mem.read_addr8(LY_ADDR), 0x90);
+    // The lcd keeps counting scanlines underneath
+    assert_eq!(cpu.mem.ly(), 3);
+
+    cpu.mem.set_stub_ly(false);
+    assert_eq!(cpu.mem.read_addr8(LY_ADDR), 3);
+}